@@ -1,9 +1,9 @@
-use std::{collections::BTreeSet, fs, sync::mpsc::channel, thread, time::Duration};
+use std::{collections::BTreeSet, fs, path::PathBuf, sync::mpsc::channel, thread, time::Duration};
 
 use aoc2024::*;
 
 use chrono::{Datelike, Local};
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 
 #[derive(Parser)]
@@ -24,11 +24,347 @@ struct Cli {
     /// Download inputs for selected days
     #[arg(long)]
     download: bool,
+
+    /// Explain the solutions of the selected days and parts, for days that support it
+    #[arg(long)]
+    explain: bool,
+
+    /// Record frames of the selected days and parts into this directory
+    #[arg(long, value_name = "DIR")]
+    visualize: Option<PathBuf>,
+
+    /// Format of the recorded frames
+    #[arg(long, value_enum, default_value_t = visualize::Format::Gif)]
+    format: visualize::Format,
+
+    /// Size in pixels of a single tile in recorded images
+    #[arg(long, default_value_t = 4)]
+    scale: usize,
+
+    /// Milliseconds between recorded frames in animations
+    #[arg(long, default_value_t = 50)]
+    delay: u64,
+
+    /// Record only every n-th frame
+    #[arg(long, default_value_t = 1)]
+    every: usize,
+
+    /// Maximum number of recorded frames per part
+    #[arg(long, default_value_t = 1000)]
+    max_frames: usize,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Tools for day 3
+    Day03 {
+        #[command(subcommand)]
+        command: Day03Command,
+    },
+
+    /// Tools for day 9
+    Day09 {
+        #[command(subcommand)]
+        command: Day09Command,
+    },
+
+    /// Tools for day 10
+    Day10 {
+        #[command(subcommand)]
+        command: Day10Command,
+    },
+
+    /// Tools for day 14
+    Day14 {
+        #[command(subcommand)]
+        command: Day14Command,
+    },
+
+    /// Tools for day 15
+    Day15 {
+        #[command(subcommand)]
+        command: Day15Command,
+    },
+
+    /// Tools for day 17
+    Day17 {
+        #[command(subcommand)]
+        command: Day17Command,
+    },
+
+    /// Tools for day 18
+    Day18 {
+        #[command(subcommand)]
+        command: Day18Command,
+    },
+
+    /// Tools for day 19
+    Day19 {
+        #[command(subcommand)]
+        command: Day19Command,
+    },
+
+    /// Tools for day 21
+    Day21 {
+        #[command(subcommand)]
+        command: Day21Command,
+    },
+
+    /// Tools for day 22
+    Day22 {
+        #[command(subcommand)]
+        command: Day22Command,
+    },
+
+    /// Tools for day 24
+    Day24 {
+        #[command(subcommand)]
+        command: Day24Command,
+    },
+}
+
+#[derive(Subcommand)]
+enum Day03Command {
+    /// Print the corrupted memory of your input with the instructions coloured
+    Highlight {
+        /// Part whose rules decide which instructions are enabled
+        #[arg(short, long, default_value_t = 2, value_parser = clap::value_parser!(u8).range(1..=2))]
+        part: u8,
+    },
+}
+
+#[derive(Subcommand)]
+enum Day09Command {
+    /// Compact the disk of your input and show the checksum
+    Compact {
+        /// Where to move the blocks of the files
+        #[arg(short, long, value_enum, default_value_t = Day09Strategy::FirstFit)]
+        strategy: Day09Strategy,
+
+        /// Also show the blocks on the disk after compacting
+        #[arg(short, long)]
+        layout: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum Day10Command {
+    /// Rate a trailhead of your input and list its hiking trails
+    Trails {
+        /// Column of the trailhead
+        x: usize,
+
+        /// Row of the trailhead
+        y: usize,
+
+        /// Maximum increase in height of a single step
+        #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u8).range(1..=9))]
+        max_climb: u8,
+
+        /// Also allow diagonal steps
+        #[arg(long)]
+        diagonal: bool,
+
+        /// Maximum number of trails to list
+        #[arg(short, long, default_value_t = 10)]
+        limit: usize,
+    },
+}
+
+#[derive(Subcommand)]
+enum Day14Command {
+    /// Find the second at which the robots form a Christmas tree and show it
+    Tree {
+        /// Also save the tree as ASCII art (.txt) or as an image (.ppm or .png)
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+
+        /// Size in pixels of a single tile in the saved image
+        #[arg(long, default_value_t = 4)]
+        scale: usize,
+    },
+}
+
+#[derive(Subcommand)]
+enum Day15Command {
+    /// Move the robot through the warehouse of your input yourself
+    Play,
+}
+
+#[derive(Subcommand)]
+enum Day17Command {
+    /// Step through the program of your input in an interactive debugger
+    Debug,
+}
+
+#[derive(Subcommand)]
+enum Day18Command {
+    /// Find the first byte of your input that cuts off the exit
+    Blocking {
+        /// How to search for the byte
+        #[arg(short, long, value_enum, default_value_t = Day18Strategy::default())]
+        strategy: Day18Strategy,
+    },
+}
+
+#[derive(Subcommand)]
+enum Day19Command {
+    /// Count and list the ways to make a design with the towels of your input
+    Arrangements {
+        /// Stripes of the design, like `brwrr`
+        design: String,
+
+        /// Maximum number of arrangements to list
+        #[arg(short, long, default_value_t = 10)]
+        limit: usize,
+    },
+}
+
+#[derive(Subcommand)]
+enum Day21Command {
+    /// Show the shortest button sequences on every keypad to type a code
+    Sequence {
+        /// Code to type on the numeric keypad
+        code: String,
+
+        /// Number of robots using a directional keypad
+        #[arg(short, long, default_value_t = 2)]
+        robots: usize,
+    },
+}
+
+#[derive(Subcommand)]
+enum Day22Command {
+    /// Find the sequence of price changes that buys the most bananas from the buyers of your input
+    Bananas {
+        /// Also count the bananas bought with this sequence of four price changes, like `-2,1,-1,3`
+        #[arg(short, long, allow_hyphen_values = true, value_parser = parse_sequence)]
+        sequence: Option<[i8; 4]>,
+
+        /// Only sell to these buyers, by their index in your input. If omitted, all buyers are used.
+        #[arg(short, long, value_delimiter = ',')]
+        buyers: Vec<usize>,
+
+        /// Number of new secret numbers of every buyer
+        #[arg(short, long, default_value_t = 2000)]
+        iterations: usize,
+    },
+}
+
+#[derive(Subcommand)]
+enum Day24Command {
+    /// Print the circuit of your input as a Graphviz DOT graph
+    Dot,
+
+    /// Add two numbers with the circuit of your input and show which z-wires are wrong
+    Add {
+        /// Number on the x-wires
+        x: u64,
+
+        /// Number on the y-wires
+        y: u64,
+    },
 }
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
 
+    if let Some(command) = cli.command {
+        return match command {
+            Command::Day03 {
+                command: Day03Command::Highlight { part },
+            } => {
+                print!("{}", day03_highlight(Part::from(part))?);
+                Ok(())
+            }
+            Command::Day09 {
+                command: Day09Command::Compact { strategy, layout },
+            } => {
+                print!("{}", day09_compact(strategy, layout)?);
+                Ok(())
+            }
+            Command::Day10 {
+                command:
+                    Day10Command::Trails {
+                        x,
+                        y,
+                        max_climb,
+                        diagonal,
+                        limit,
+                    },
+            } => {
+                let (rating, trails) = day10_trails(x, y, max_climb, diagonal, limit)?;
+                println!("{rating} trails");
+                for trail in trails {
+                    println!("{trail}");
+                }
+                Ok(())
+            }
+            Command::Day14 {
+                command: Day14Command::Tree { output, scale },
+            } => {
+                print!("{}", day14_tree(output.as_deref(), scale)?);
+                Ok(())
+            }
+            Command::Day15 {
+                command: Day15Command::Play,
+            } => day15_play(),
+            Command::Day17 {
+                command: Day17Command::Debug,
+            } => day17_debug(),
+            Command::Day18 {
+                command: Day18Command::Blocking { strategy },
+            } => {
+                println!("{}", day18_blocking(strategy)?);
+                Ok(())
+            }
+            Command::Day19 {
+                command: Day19Command::Arrangements { design, limit },
+            } => {
+                let (count, arrangements) = day19_arrangements(&design, limit)?;
+                println!("{count} arrangements");
+                for arrangement in arrangements {
+                    println!("{arrangement}");
+                }
+                Ok(())
+            }
+            Command::Day21 {
+                command: Day21Command::Sequence { code, robots },
+            } => {
+                for sequence in day21_sequences(&code, robots)? {
+                    println!("{sequence}");
+                }
+                Ok(())
+            }
+            Command::Day22 {
+                command:
+                    Day22Command::Bananas {
+                        sequence,
+                        buyers,
+                        iterations,
+                    },
+            } => {
+                print!("{}", day22_bananas(sequence, &buyers, iterations)?);
+                Ok(())
+            }
+            Command::Day24 {
+                command: Day24Command::Dot,
+            } => {
+                print!("{}", day24_dot()?);
+                Ok(())
+            }
+            Command::Day24 {
+                command: Day24Command::Add { x, y },
+            } => {
+                print!("{}", day24_add(x, y)?);
+                Ok(())
+            }
+        };
+    }
+
     let days = if cli.all {
         (1..=DAYS).collect()
     } else if cli.day.is_empty() {
@@ -47,6 +383,32 @@ fn main() -> Result<()> {
 
     if cli.download {
         download(&days)?;
+    } else if cli.explain {
+        for &day in &days {
+            for &part in &parts {
+                println!(
+                    "Day: {day:02}, part {part}:\n{}",
+                    explain(day, Part::from(part))
+                );
+            }
+        }
+    } else if let Some(dir) = &cli.visualize {
+        for &day in &days {
+            for &part in &parts {
+                visualize::start(visualize::Options {
+                    format: cli.format,
+                    dir: dir.clone(),
+                    name: format!("day{day:02}_part{part}"),
+                    scale: cli.scale,
+                    delay: Duration::from_millis(cli.delay),
+                    every: cli.every,
+                    max_frames: cli.max_frames,
+                })?;
+                let result = run(day, Part::from(part));
+                let frames = visualize::finish()?;
+                println!("Day: {day:02}, part {part}: {result} ({frames} frames recorded)");
+            }
+        }
     } else {
         let (sender, receiver) = channel();
         days.par_iter().for_each_with(sender, |sender, &day| {
@@ -67,6 +429,16 @@ fn main() -> Result<()> {
     Ok(())
 }
 
+fn parse_sequence(s: &str) -> Result<[i8; 4], String> {
+    let changes = s
+        .split(',')
+        .map(|change| change.trim().parse::<i8>().map_err(|err| err.to_string()))
+        .collect::<Result<Vec<_>, _>>()?;
+    changes
+        .try_into()
+        .map_err(|_| "a sequence has four price changes".to_string())
+}
+
 fn download(days: &BTreeSet<u8>) -> Result<()> {
     let client = reqwest::blocking::Client::new();
     let session = fs::read_to_string(".session")?;
@@ -1,4 +1,4 @@
-use std::cmp::Ordering::*;
+use std::{cmp::Ordering::*, fmt::Display};
 
 use nom::{
     character::complete::{newline, space1, u32},
@@ -14,67 +14,192 @@ day! {
     Parsed = Vec<Vec<Output>>,
 }
 
-#[derive(PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
 enum Ordering {
     Increasing,
     Decreasing,
 }
 
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum Violation {
+    Equal,
+    TooSteep,
+    DirectionChange,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Verdict {
+    Safe,
+    /// The first level that violates the rules, and the indices of the fewest levels to remove
+    /// to make the report safe, if that's within the tolerance.
+    Unsafe {
+        index: usize,
+        violation: Violation,
+        fix: Option<Vec<usize>>,
+    },
+}
+
+impl Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Violation::Equal => "is not increasing or decreasing",
+            Violation::TooSteep => "is too steep",
+            Violation::DirectionChange => "changes direction",
+        })
+    }
+}
+
+impl Verdict {
+    fn is_safe(&self) -> bool {
+        matches!(self, Verdict::Safe | Verdict::Unsafe { fix: Some(_), .. })
+    }
+}
+
 impl Day {
+    pub fn explain(part: Part) -> Result<String> {
+        Ok(Self::explanation(
+            &Parser::parse(include_str!("input.txt"))?,
+            part,
+        ))
+    }
+
     fn part1(reports: Parsed) -> Result<Output> {
-        Ok(reports
-            .into_iter()
-            .filter(|report| Self::is_safe(report, false, None))
-            .count() as Output)
+        Ok(Self::count_safe(&reports, 0))
     }
 
     fn part2(reports: Parsed) -> Result<Output> {
-        Ok(reports
-            .into_iter()
-            .filter(|report| Self::is_safe(report, true, None))
-            .count() as Output)
+        Ok(Self::count_safe(&reports, 1))
     }
 
-    fn is_safe(report: &[Output], tolerance: bool, exclude: Option<usize>) -> bool {
-        if exclude == Some(0) {
-            return Self::is_safe(&report[1..], false, None);
+    fn count_safe(reports: &[Vec<Output>], tolerance: usize) -> Output {
+        reports
+            .iter()
+            .filter(|report| Self::analyse(report, tolerance).is_safe())
+            .count() as Output
+    }
+
+    fn tolerance(part: Part) -> usize {
+        match part {
+            Part1 => 0,
+            Part2 => 1,
         }
+    }
 
-        let mut iter = report.iter().enumerate();
-        let mut prev = iter.next().unwrap().1;
-        let mut prev_ordering = None;
+    /// Checks a report, allowing up to `tolerance` levels to be removed.
+    fn analyse(report: &[Output], tolerance: usize) -> Verdict {
+        let Some((index, violation)) = Self::first_violation(report) else {
+            return Verdict::Safe;
+        };
+        let fix = [Increasing, Decreasing]
+            .into_iter()
+            .map(|ordering| Self::fewest_removals(report, ordering))
+            .min_by_key(Vec::len)
+            .filter(|removals| removals.len() <= tolerance);
+        Verdict::Unsafe {
+            index,
+            violation,
+            fix,
+        }
+    }
 
-        for (idx, next) in iter {
-            if Some(idx) == exclude {
-                continue;
+    fn first_violation(report: &[Output]) -> Option<(usize, Violation)> {
+        let mut ordering = None;
+        for (index, pair) in report.windows(2).enumerate() {
+            match Self::check_ordering(pair[0], pair[1]) {
+                Err(violation) => return Some((index + 1, violation)),
+                Ok(next) if *ordering.get_or_insert(next) != next => {
+                    return Some((index + 1, Violation::DirectionChange))
+                }
+                Ok(_) => {}
             }
+        }
+        None
+    }
 
-            let next_ordering = Self::check_ordering(*prev, *next);
-            if next_ordering.is_none()
-                || (prev_ordering.is_some() && next_ordering != prev_ordering)
-            {
-                if tolerance {
-                    return Self::is_safe(report, false, Some(idx))
-                        || Self::is_safe(report, false, Some(idx - 1))
-                        || (idx >= 2 && Self::is_safe(report, false, Some(idx - 2)));
-                } else {
-                    return false;
+    /// The fewest levels to remove so the remaining levels all follow `ordering`. For every
+    /// level this finds the fewest removals before it when it is kept, so the removed levels
+    /// can be traced back from the best last level.
+    fn fewest_removals(report: &[Output], ordering: Ordering) -> Vec<usize> {
+        let mut removals = Vec::<(usize, Option<usize>)>::with_capacity(report.len());
+        for next in 0..report.len() {
+            let mut best = (next, None);
+            for prev in 0..next {
+                let count = removals[prev].0 + next - prev - 1;
+                if count < best.0
+                    && Self::check_ordering(report[prev], report[next]) == Ok(ordering)
+                {
+                    best = (count, Some(prev));
                 }
-            } else {
-                prev = next;
-                prev_ordering = next_ordering;
             }
+            removals.push(best);
+        }
+
+        let Some(mut last) =
+            (0..report.len()).min_by_key(|&last| removals[last].0 + report.len() - 1 - last)
+        else {
+            return Vec::new();
+        };
+        let mut removed = Vec::from_iter(last + 1..report.len());
+        while let (_, Some(prev)) = removals[last] {
+            removed.extend(prev + 1..last);
+            last = prev;
         }
-        true
+        removed.extend(0..last);
+        removed.sort_unstable();
+        removed
     }
 
-    fn check_ordering(prev: Output, next: Output) -> Option<Ordering> {
+    fn check_ordering(prev: Output, next: Output) -> std::result::Result<Ordering, Violation> {
         match prev.cmp(&next) {
-            Equal => None,
-            Less => (next - prev <= 3).then_some(Increasing),
-            Greater => (prev - next <= 3).then_some(Decreasing),
+            Equal => Err(Violation::Equal),
+            _ if prev.abs_diff(next) > 3 => Err(Violation::TooSteep),
+            Less => Ok(Increasing),
+            Greater => Ok(Decreasing),
         }
     }
+
+    fn explanation(reports: &[Vec<Output>], part: Part) -> String {
+        let tolerance = Self::tolerance(part);
+        let mut explanation = String::new();
+        for report in reports {
+            explanation += &report
+                .iter()
+                .map(Output::to_string)
+                .collect::<Vec<_>>()
+                .join(" ");
+            explanation += &match Self::analyse(report, tolerance) {
+                Verdict::Safe => ": safe\n".to_string(),
+                Verdict::Unsafe {
+                    index,
+                    violation,
+                    fix,
+                } => {
+                    let mut line = format!(
+                        ": {} -> {} at index {index} {violation}",
+                        report[index - 1],
+                        report[index]
+                    );
+                    match fix {
+                        Some(fix) => {
+                            let removed = fix
+                                .iter()
+                                .map(|&index| format!("index {index} ({})", report[index]))
+                                .collect::<Vec<_>>()
+                                .join(", ");
+                            line += &format!(", safe without {removed}\n");
+                        }
+                        None => line += ", unsafe\n",
+                    }
+                    line
+                }
+            };
+        }
+        explanation += &format!(
+            "{} reports are safe\n",
+            Self::count_safe(reports, tolerance)
+        );
+        explanation
+    }
 }
 
 impl Parser {
@@ -102,4 +227,103 @@ mod tests {
     test_example!("example1", Part1, 2);
 
     test_example!("example1", Part2, 4);
+
+    #[test]
+    fn verdicts() {
+        assert_eq!(Day::analyse(&[7, 6, 4, 2, 1], 1), Verdict::Safe);
+        assert_eq!(
+            Day::analyse(&[1, 2, 7, 8, 9], 1),
+            Verdict::Unsafe {
+                index: 2,
+                violation: Violation::TooSteep,
+                fix: None
+            }
+        );
+        assert_eq!(
+            Day::analyse(&[8, 6, 4, 4, 1], 0),
+            Verdict::Unsafe {
+                index: 3,
+                violation: Violation::Equal,
+                fix: None
+            }
+        );
+        assert_eq!(
+            Day::analyse(&[9, 1, 2, 3, 2, 5], 2),
+            Verdict::Unsafe {
+                index: 1,
+                violation: Violation::TooSteep,
+                fix: Some(vec![0, 4])
+            }
+        );
+        assert_eq!(
+            Day::first_violation(&[1, 3, 2, 4, 5]),
+            Some((2, Violation::DirectionChange))
+        );
+    }
+
+    /// Tries every way to remove up to `tolerance` levels.
+    fn brute_force(report: &[Output], tolerance: usize) -> bool {
+        fn safe_without(report: &[Output], from: usize, tolerance: usize) -> bool {
+            Day::first_violation(report).is_none()
+                || (tolerance > 0
+                    && (from..report.len()).any(|index| {
+                        let mut removed = report.to_vec();
+                        removed.remove(index);
+                        safe_without(&removed, index, tolerance - 1)
+                    }))
+        }
+        safe_without(report, 0, tolerance)
+    }
+
+    #[test]
+    fn tolerance_matches_brute_force() {
+        let mut random = Random::new(2024);
+        for _ in 0..2000 {
+            let len = 1 + random.below(8) as usize;
+            let mut level = 10 + random.below(10) as Output;
+            let report = (0..len)
+                .map(|_| {
+                    level = (level + random.below(9) as Output).saturating_sub(4);
+                    level
+                })
+                .collect::<Vec<_>>();
+            for tolerance in 0..=3 {
+                let verdict = Day::analyse(&report, tolerance);
+                assert_eq!(
+                    verdict.is_safe(),
+                    brute_force(&report, tolerance),
+                    "{report:?} with tolerance {tolerance}"
+                );
+                if let Verdict::Unsafe { fix: Some(fix), .. } = verdict {
+                    let fixed = (0..report.len())
+                        .filter(|index| !fix.contains(index))
+                        .map(|index| report[index])
+                        .collect::<Vec<_>>();
+                    assert_eq!(Day::first_violation(&fixed), None);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn explanation() -> Result<()> {
+        let reports = Parser::parse(include_str!("example1.txt"))?;
+        let explanation = Day::explanation(&reports, Part2);
+        let lines = explanation.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], "7 6 4 2 1: safe");
+        assert_eq!(
+            lines[1],
+            "1 2 7 8 9: 2 -> 7 at index 2 is too steep, unsafe"
+        );
+        assert_eq!(
+            lines[3],
+            "1 3 2 4 5: 3 -> 2 at index 2 changes direction, safe without index 2 (2)"
+        );
+        assert_eq!(lines[6], "4 reports are safe");
+        assert_eq!(
+            Day::explanation(&reports, Part1).lines().last(),
+            Some("2 reports are safe")
+        );
+        Ok(())
+    }
 }
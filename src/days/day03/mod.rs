@@ -1,75 +1,158 @@
+use std::{fmt::Write, ops::Range};
+
 use nom::{
     branch::alt,
-    bytes::complete::tag,
-    character::complete::{anychar, u32},
-    combinator::{all_consuming, map, map_res},
-    error::ErrorKind,
-    multi::many1,
-    sequence::{preceded, separated_pair, terminated},
+    bytes::complete::{tag, take_while_m_n},
+    combinator::{map, map_res},
+    sequence::{delimited, pair, separated_pair},
 };
 
 use crate::*;
+use visualize::Rgb;
 
 day! {
     Output = u32,
-    Parsed = Vec<Instruction>,
+    Parsed = Vec<Token>,
+}
+
+/// An instruction of the form `name(a,b)`.
+#[derive(Debug)]
+struct Operation {
+    name: &'static str,
+    apply: fn(Output, Output) -> Output,
 }
 
+/// The operations the scanner recognises, add an entry to support another `name(a,b)` form.
+const OPERATIONS: &[Operation] = &[Operation {
+    name: "mul",
+    apply: |a, b| a * b,
+}];
+
 #[derive(Debug)]
 enum Instruction {
-    Mul(Output, Output),
+    Operation(&'static Operation, Output, Output),
     Do,
     Dont,
-    Invalid,
+}
+
+/// A recognised instruction and the bytes of the input it was read from.
+#[derive(Debug)]
+struct Token {
+    span: Range<usize>,
+    instruction: Instruction,
+}
+
+impl Instruction {
+    fn value(&self) -> Output {
+        match self {
+            Instruction::Operation(operation, a, b) => (operation.apply)(*a, *b),
+            _ => 0,
+        }
+    }
 }
 
 impl Day {
-    fn part1(parsed: Parsed) -> Result<Output> {
-        Ok(parsed
-            .into_iter()
-            .filter_map(|instruction| match instruction {
-                Instruction::Mul(a, b) => Some(a * b),
-                _ => None,
-            })
+    /// Your input with the instructions coloured: enabled operations green, disabled
+    /// operations red and `do()` and `don't()` yellow.
+    pub fn highlight(part: Part) -> Result<String> {
+        let input = include_str!("input.txt");
+        Ok(Self::highlighted(input, &Parser::parse(input)?, part))
+    }
+
+    fn part1(tokens: Parsed) -> Result<Output> {
+        Ok(Self::enabled(&tokens, Part1)
+            .map(|(token, _)| token.instruction.value())
+            .sum())
+    }
+
+    fn part2(tokens: Parsed) -> Result<Output> {
+        Ok(Self::enabled(&tokens, Part2)
+            .filter(|&(_, enabled)| enabled)
+            .map(|(token, _)| token.instruction.value())
             .sum())
     }
 
-    fn part2(parsed: Parsed) -> Result<Output> {
-        Ok(parsed
-            .into_iter()
-            .fold((0, true), |(sum, enabled), instruction| match instruction {
-                Instruction::Mul(a, b) => (sum + if enabled { a * b } else { 0 }, enabled),
-                Instruction::Do => (sum, true),
-                Instruction::Dont => (sum, false),
-                _ => (sum, enabled),
-            })
-            .0)
+    /// Every token and whether it is enabled. In part 1 everything is, in part 2 `do()` and
+    /// `don't()` turn the operations after them on and off.
+    fn enabled(tokens: &[Token], part: Part) -> impl Iterator<Item = (&Token, bool)> {
+        let mut enabled = true;
+        tokens.iter().map(move |token| {
+            match (part, &token.instruction) {
+                (Part2, Instruction::Do) => enabled = true,
+                (Part2, Instruction::Dont) => enabled = false,
+                _ => {}
+            }
+            (token, enabled)
+        })
+    }
+
+    fn highlighted(input: &str, tokens: &[Token], part: Part) -> String {
+        let mut highlighted = String::with_capacity(input.len() * 2);
+        let mut end = 0;
+        for (token, enabled) in Self::enabled(tokens, part) {
+            let Rgb(r, g, b) = match token.instruction {
+                Instruction::Operation(..) if enabled => Rgb::GREEN,
+                Instruction::Operation(..) => Rgb::RED,
+                Instruction::Do | Instruction::Dont => Rgb::YELLOW,
+            };
+            highlighted += &input[end..token.span.start];
+            write!(
+                highlighted,
+                "\x1b[38;2;{r};{g};{b}m{}\x1b[0m",
+                &input[token.span.clone()]
+            )
+            .unwrap();
+            end = token.span.end;
+        }
+        highlighted += &input[end..];
+        highlighted.push('\n');
+        highlighted
     }
 }
 
 impl Parser {
     fn parse(input: &'static str) -> Result<Parsed> {
-        Ok(all_consuming(Self::instructions)(input)?.1)
+        Ok(Self::tokenize(input, OPERATIONS))
     }
 
-    fn instructions(s: &'static str) -> IResult<Parsed> {
-        many1(Self::instruction)(s)
+    /// Scans the corrupted memory for instructions, skipping everything in between.
+    fn tokenize(input: &'static str, operations: &'static [Operation]) -> Vec<Token> {
+        let mut tokens = Vec::new();
+        let mut rest = input;
+        while let Some(c) = rest.chars().next() {
+            match Self::instruction(rest, operations) {
+                Some((next, instruction)) => {
+                    tokens.push(Token {
+                        span: input.len() - rest.len()..input.len() - next.len(),
+                        instruction,
+                    });
+                    rest = next;
+                }
+                None => rest = &rest[c.len_utf8()..],
+            }
+        }
+        tokens
     }
 
-    fn instruction(s: &'static str) -> IResult<Instruction> {
-        alt((Self::mul, Self::parse_do, Self::dont, Self::invalid))(s)
+    fn instruction(
+        s: &'static str,
+        operations: &'static [Operation],
+    ) -> Option<(&'static str, Instruction)> {
+        alt((Self::parse_do, Self::dont))(s).ok().or_else(|| {
+            operations
+                .iter()
+                .find_map(|operation| Self::operation(s, operation).ok())
+        })
     }
 
-    fn mul(s: &'static str) -> IResult<Instruction> {
+    fn operation(s: &'static str, operation: &'static Operation) -> IResult<'static, Instruction> {
         map(
-            preceded(
-                tag("mul("),
-                terminated(
-                    separated_pair(Self::u32_max_999, tag(","), Self::u32_max_999),
-                    tag(")"),
-                ),
+            delimited(
+                pair(tag(operation.name), tag("(")),
+                separated_pair(Self::u32_max_999, tag(","), Self::u32_max_999),
+                tag(")"),
             ),
-            |(a, b)| Instruction::Mul(a, b),
+            |(a, b)| Instruction::Operation(operation, a, b),
         )(s)
     }
 
@@ -81,18 +164,12 @@ impl Parser {
         map(tag("don't()"), |_| Instruction::Dont)(s)
     }
 
-    fn invalid(s: &'static str) -> IResult<Instruction> {
-        map(anychar, |_| Instruction::Invalid)(s)
-    }
-
+    /// A number of 1 to 3 digits, so `1234` and `0999` are not accepted.
     fn u32_max_999(s: &'static str) -> IResult<Output> {
-        map_res(u32, |n| {
-            if n <= 999 {
-                Ok(n)
-            } else {
-                Err(nom::Err::Error(("Number too large", ErrorKind::Digit)))
-            }
-        })(s)
+        map_res(
+            take_while_m_n(1, 3, |c: char| c.is_ascii_digit()),
+            str::parse,
+        )(s)
     }
 }
 
@@ -107,4 +184,72 @@ mod tests {
     test_example!("example1", Part1, 161);
 
     test_example!("example2", Part2, 48);
+
+    #[test]
+    fn spans() {
+        let input = include_str!("example2.txt");
+        let tokens = Parser::tokenize(input, OPERATIONS);
+        let spans = tokens
+            .iter()
+            .map(|token| &input[token.span.clone()])
+            .collect::<Vec<_>>();
+        assert_eq!(
+            spans,
+            [
+                "mul(2,4)",
+                "don't()",
+                "mul(5,5)",
+                "mul(11,8)",
+                "do()",
+                "mul(8,5)"
+            ]
+        );
+        assert_eq!(tokens[0].span, 1..9);
+    }
+
+    #[test]
+    fn numbers() {
+        let values = |input| {
+            Parser::tokenize(input, OPERATIONS)
+                .iter()
+                .map(|token| token.instruction.value())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(values("mul(999,1)mul(1000,1)mul(0999,1)mul(1,1234)"), [999]);
+        assert_eq!(values("mul(01,002)mul( 1,2)mul(1,2 )mul(,2)"), [2]);
+        assert_eq!(values("mmul(2,3)mul(mul(4,5)é)"), [6, 20]);
+    }
+
+    #[test]
+    fn operations() {
+        const EXTENDED: &[Operation] = &[
+            Operation {
+                name: "mul",
+                apply: |a, b| a * b,
+            },
+            Operation {
+                name: "add",
+                apply: |a, b| a + b,
+            },
+        ];
+        let tokens = Parser::tokenize("add(3,4)mul(3,4)don't()add(1,1)", EXTENDED);
+        assert_eq!(
+            Day::part1(Parser::tokenize("add(3,4)", OPERATIONS)).ok(),
+            Some(0)
+        );
+        assert_eq!(Day::part2(tokens).ok(), Some(19));
+    }
+
+    #[test]
+    fn highlight() -> Result<()> {
+        let input = "xmul(2,4)don't()mul(5,5)+";
+        let highlighted = Day::highlighted(input, &Parser::parse(input)?, Part2);
+        assert_eq!(
+            highlighted,
+            "x\x1b[38;2;40;200;70mmul(2,4)\x1b[0m\
+             \x1b[38;2;240;210;40mdon't()\x1b[0m\
+             \x1b[38;2;220;40;40mmul(5,5)\x1b[0m+\n"
+        );
+        Ok(())
+    }
 }
@@ -1,3 +1,7 @@
+use std::collections::BTreeSet;
+
+use anyhow::bail;
+
 use crate::*;
 
 day! {
@@ -5,113 +9,205 @@ day! {
     Parsed = Puzzle,
 }
 
+const DIRECTIONS: [(isize, isize); 8] = [
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (-1, 0),
+    (1, 0),
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+];
+
+/// The positions `(x, y)` of the letters of a match.
+type Cells = Vec<(usize, usize)>;
+
 struct Puzzle {
-    lines: Vec<String>,
+    grid: Vec<Vec<u8>>,
 }
 
-impl FromIterator<String> for Puzzle {
-    fn from_iter<T: IntoIterator<Item = String>>(iter: T) -> Self {
+/// A block of letters to find in the puzzle, where `.` matches any letter.
+#[derive(Clone, PartialEq, Eq)]
+struct Pattern {
+    rows: Vec<Vec<u8>>,
+}
+
+impl Pattern {
+    fn new(rows: &[&str]) -> Result<Self> {
+        let width = rows.first().map_or(0, |row| row.len());
+        if width == 0 {
+            bail!("A pattern needs at least one letter");
+        }
+        if rows.iter().any(|row| row.len() != width) {
+            bail!("All rows of a pattern must have the same length");
+        }
+        Ok(Self {
+            rows: rows.iter().map(|row| row.as_bytes().to_vec()).collect(),
+        })
+    }
+
+    fn x_mas() -> Result<Self> {
+        Self::new(&["M.S", ".A.", "M.S"])
+    }
+
+    fn width(&self) -> usize {
+        self.rows.first().map_or(0, Vec::len)
+    }
+
+    fn rotate(&self) -> Self {
         Self {
-            lines: Vec::from_iter(iter),
+            rows: (0..self.width())
+                .map(|x| self.rows.iter().rev().map(|row| row[x]).collect())
+                .collect(),
         }
     }
+
+    /// The pattern turned in all four directions, without the ones that look the same.
+    fn rotations(&self) -> Vec<Self> {
+        let mut rotations = vec![self.clone()];
+        while rotations.len() < 4 {
+            let next = rotations[rotations.len() - 1].rotate();
+            if next == rotations[0] {
+                break;
+            }
+            rotations.push(next);
+        }
+        rotations
+    }
 }
 
 impl Puzzle {
-    fn count_xmas_part1(&self) -> Output {
-        self.lines
-            .iter()
-            .enumerate()
-            .flat_map(|(y, line)| {
-                line.chars()
-                    .enumerate()
-                    .filter(|(_, c)| *c == 'X')
-                    .map(move |(x, _)| {
-                        let mut count = 0;
-                        for offset_y in -1..=1isize {
-                            for offset_x in -1..=1isize {
-                                if (offset_y != 0 || offset_x != 0)
-                                    && self.check_mas(y as isize, x as isize, offset_y, offset_x)
-                                {
-                                    count += 1;
-                                }
+    fn width(&self) -> usize {
+        self.grid.first().map_or(0, Vec::len)
+    }
+
+    fn get(&self, x: isize, y: isize) -> Option<u8> {
+        let row = self.grid.get(usize::try_from(y).ok()?)?;
+        row.get(usize::try_from(x).ok()?).copied()
+    }
+
+    /// Every place where one of the words is written, in any of the eight directions. Words
+    /// that read the same in more than one direction are only counted once.
+    fn find_words(&self, words: &[&str]) -> Result<Vec<Cells>> {
+        if words.iter().any(|word| word.is_empty()) {
+            bail!("Can not search for an empty word");
+        }
+        let mut found = BTreeSet::new();
+        let mut matches = Vec::new();
+        for y in 0..self.grid.len() {
+            for x in 0..self.width() {
+                for (dx, dy) in DIRECTIONS {
+                    for word in words {
+                        let cells = (0..word.len() as isize)
+                            .map(|i| (x as isize + dx * i, y as isize + dy * i))
+                            .collect::<Vec<_>>();
+                        if cells
+                            .iter()
+                            .zip(word.bytes())
+                            .all(|(&(x, y), letter)| self.get(x, y) == Some(letter))
+                        {
+                            let cells = cells
+                                .into_iter()
+                                .map(|(x, y)| (x as usize, y as usize))
+                                .collect::<Cells>();
+                            if found.insert((*word, BTreeSet::from_iter(cells.iter().copied()))) {
+                                matches.push(cells);
                             }
                         }
-                        count
-                    })
-            })
-            .sum()
+                    }
+                }
+            }
+        }
+        Ok(matches)
     }
 
-    fn check_mas(&self, y: isize, x: isize, offset_y: isize, offset_x: isize) -> bool {
-        self.get(y + offset_y, x + offset_x) == "M"
-            && self.get(y + offset_y * 2, x + offset_x * 2) == "A"
-            && self.get(y + offset_y * 3, x + offset_x * 3) == "S"
+    /// Every place where the pattern fits, turned in any direction.
+    fn find_pattern(&self, pattern: &Pattern) -> Vec<Cells> {
+        let mut matches = Vec::new();
+        for pattern in pattern.rotations() {
+            let letters = pattern
+                .rows
+                .iter()
+                .enumerate()
+                .flat_map(|(y, row)| row.iter().enumerate().map(move |(x, &c)| (x, y, c)))
+                .filter(|&(_, _, c)| c != b'.')
+                .collect::<Vec<_>>();
+            for y in 0..(self.grid.len() + 1).saturating_sub(pattern.rows.len()) {
+                for x in 0..(self.width() + 1).saturating_sub(pattern.width()) {
+                    if letters
+                        .iter()
+                        .all(|&(dx, dy, c)| self.grid[y + dy][x + dx] == c)
+                    {
+                        matches.push(
+                            letters
+                                .iter()
+                                .map(|&(dx, dy, _)| (x + dx, y + dy))
+                                .collect(),
+                        );
+                    }
+                }
+            }
+        }
+        matches
     }
 
-    fn get(&self, y: isize, x: isize) -> &str {
-        if y >= 0 && y < self.lines.len() as isize && x >= 0 && x < self.lines[0].len() as isize {
-            &self.lines[y as usize][x as usize..=x as usize]
-        } else {
-            "."
+    /// The puzzle with every letter that is not part of a match replaced by a `.`.
+    fn highlight(&self, matches: &[Cells]) -> String {
+        let mut grid = vec![vec![b'.'; self.width()]; self.grid.len()];
+        for &(x, y) in matches.iter().flatten() {
+            grid[y][x] = self.grid[y][x];
         }
+        grid.into_iter()
+            .map(|row| String::from_utf8_lossy(&row).into_owned() + "\n")
+            .collect()
     }
 
-    fn count_xmas_part2(&self) -> Output {
-        self.lines
-            .iter()
-            .enumerate()
-            .skip(1)
-            .take(self.lines.len() - 2)
-            .flat_map(|(y, line)| {
-                line.chars()
-                    .enumerate()
-                    .skip(1)
-                    .take(self.lines[0].len() - 2)
-                    .filter(|(_, c)| *c == 'A')
-                    .filter(move |(x, _)| {
-                        let mut count_m = 0;
-                        let mut count_s = 0;
-
-                        self.check_m_s(y - 1, x - 1, &mut count_m, &mut count_s, 1)
-                            && self.check_m_s(y + 1, x + 1, &mut count_m, &mut count_s, 1)
-                            && self.check_m_s(y - 1, x + 1, &mut count_m, &mut count_s, 2)
-                            && self.check_m_s(y + 1, x - 1, &mut count_m, &mut count_s, 2)
-                    })
-            })
-            .count()
-    }
-
-    fn check_m_s(
-        &self,
-        y: usize,
-        x: usize,
-        count_m: &mut usize,
-        count_s: &mut usize,
-        max_count: usize,
-    ) -> bool {
-        match &self.lines[y][x..=x] {
-            "M" => *count_m += 1,
-            "S" => *count_s += 1,
-            _ => return false,
+    fn matches(&self, part: Part) -> Result<Vec<Cells>> {
+        match part {
+            Part1 => self.find_words(&["XMAS"]),
+            Part2 => Ok(self.find_pattern(&Pattern::x_mas()?)),
         }
-        *count_m <= max_count && *count_s <= max_count
     }
 }
 
 impl Day {
+    pub fn explain(part: Part) -> Result<String> {
+        Self::explanation(&Parser::parse(include_str!("input.txt"))?, part)
+    }
+
     fn part1(puzzle: Parsed) -> Result<Output> {
-        Ok(puzzle.count_xmas_part1())
+        Ok(puzzle.matches(Part1)?.len())
     }
 
     fn part2(puzzle: Parsed) -> Result<Output> {
-        Ok(puzzle.count_xmas_part2())
+        Ok(puzzle.matches(Part2)?.len())
+    }
+
+    fn explanation(puzzle: &Puzzle, part: Part) -> Result<String> {
+        let matches = puzzle.matches(part)?;
+        Ok(format!(
+            "{}{} appears {} times\n",
+            puzzle.highlight(&matches),
+            match part {
+                Part1 => "XMAS",
+                Part2 => "X-MAS",
+            },
+            matches.len()
+        ))
     }
 }
 
 impl Parser {
     fn parse(input: &'static str) -> Result<Parsed> {
-        Ok(input.lines().map(String::from).collect())
+        let grid = input
+            .lines()
+            .map(|line| line.as_bytes().to_vec())
+            .collect::<Vec<_>>();
+        if grid.iter().any(|row| row.len() != grid[0].len()) {
+            bail!("All rows of the puzzle must have the same length");
+        }
+        Ok(Puzzle { grid })
     }
 }
 
@@ -126,4 +222,69 @@ mod tests {
     test_example!("example1", Part1, 18);
 
     test_example!("example1", Part2, 9);
+
+    #[test]
+    fn explanation() -> Result<()> {
+        let puzzle = Parser::parse(include_str!("example1.txt"))?;
+        assert_eq!(
+            Day::explanation(&puzzle, Part1)?,
+            "....XXMAS.\n\
+             .SAMXMS...\n\
+             ...S..A...\n\
+             ..A.A.MS.X\n\
+             XMASAMX.MM\n\
+             X.....XA.A\n\
+             S.S.S.S.SS\n\
+             .A.A.A.A.A\n\
+             ..M.M.M.MM\n\
+             .X.X.XMASX\n\
+             XMAS appears 18 times\n"
+        );
+        assert_eq!(
+            Day::explanation(&puzzle, Part2)?,
+            ".M.S......\n\
+             ..A..MSMS.\n\
+             .M.S.MAA..\n\
+             ..A.ASMSM.\n\
+             .M.S.M....\n\
+             ..........\n\
+             S.S.S.S.S.\n\
+             .A.A.A.A..\n\
+             M.M.M.M.M.\n\
+             ..........\n\
+             X-MAS appears 9 times\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn words_and_patterns() -> Result<()> {
+        let puzzle = Parser::parse("ABC\nDEF\nGHI")?;
+        assert_eq!(puzzle.find_words(&["CEG"])?, [vec![(2, 0), (1, 1), (0, 2)]]);
+        assert_eq!(puzzle.find_words(&["FED", "HEB", "AEIX"])?.len(), 2);
+        assert!(puzzle.find_words(&[""]).is_err());
+
+        assert_eq!(
+            puzzle.find_pattern(&Pattern::new(&["D.", ".H"])?),
+            [vec![(0, 1), (1, 2)]]
+        );
+        assert_eq!(Pattern::new(&["A.", ".A"])?.rotations().len(), 2);
+        assert_eq!(Pattern::new(&["A"])?.rotations().len(), 1);
+        assert_eq!(Pattern::x_mas()?.rotations().len(), 4);
+        assert!(Pattern::new(&["AB", "C"]).is_err());
+        assert!(Pattern::new(&[]).is_err());
+        assert!(Pattern::new(&[""]).is_err());
+
+        let palindromes = Parser::parse("ABA\nXEX")?;
+        assert_eq!(
+            palindromes.find_words(&["ABA"])?,
+            [vec![(0, 0), (1, 0), (2, 0)]]
+        );
+        assert_eq!(palindromes.find_words(&["E"])?, [vec![(1, 1)]]);
+        assert_eq!(palindromes.find_words(&["X", "XE"])?.len(), 4);
+        assert_eq!(palindromes.find_words(&["XEX", "X"])?.len(), 3);
+        assert_eq!(puzzle.find_words(&["FED", "DEF"])?.len(), 2);
+        assert!(Parser::parse("ABC\nDE").is_err());
+        Ok(())
+    }
 }
@@ -1,8 +1,9 @@
-use std::cmp::Ordering;
+use std::collections::HashSet;
 
+use anyhow::bail;
 use nom::{
     bytes::complete::tag,
-    character::complete::{newline, u8},
+    character::complete::{newline, u32},
     combinator::{all_consuming, map},
     multi::separated_list1,
     sequence::{pair, separated_pair},
@@ -28,23 +29,49 @@ struct OrderingRule {
     after: PageNumber,
 }
 
-struct OrderingRulesSet {
-    ordering_rules_set: [bool; 10_000],
+/// The ordering rules as a graph with an edge from the page that has to be printed first to
+/// the page after it.
+enum OrderingRulesSet {
+    /// Adjacency matrix, for page numbers below `size`
+    Dense {
+        size: PageNumber,
+        edges: Vec<bool>,
+    },
+    Sparse(HashSet<(PageNumber, PageNumber)>),
 }
 
-impl From<&Vec<OrderingRule>> for OrderingRulesSet {
-    fn from(ordering_rules: &Vec<OrderingRule>) -> Self {
-        let mut ordering_rules_set = [false; 10_000];
-        for ordering_rule in ordering_rules {
-            ordering_rules_set[ordering_rule.before * 100 + ordering_rule.after] = true;
+impl OrderingRulesSet {
+    const MAX_DENSE_SIZE: PageNumber = 128;
+
+    fn new(ordering_rules: &[OrderingRule]) -> Self {
+        let size = ordering_rules
+            .iter()
+            .map(|rule| rule.before.max(rule.after) + 1)
+            .max()
+            .unwrap_or(0);
+        if size <= Self::MAX_DENSE_SIZE {
+            let mut edges = vec![false; size * size];
+            for rule in ordering_rules {
+                edges[rule.before * size + rule.after] = true;
+            }
+            Self::Dense { size, edges }
+        } else {
+            Self::Sparse(
+                ordering_rules
+                    .iter()
+                    .map(|rule| (rule.before, rule.after))
+                    .collect(),
+            )
         }
-        Self { ordering_rules_set }
     }
-}
 
-impl OrderingRulesSet {
-    fn contains(&self, before: &PageNumber, after: &PageNumber) -> bool {
-        self.ordering_rules_set[before * 100 + after]
+    fn contains(&self, before: PageNumber, after: PageNumber) -> bool {
+        match self {
+            Self::Dense { size, edges } => {
+                before < *size && after < *size && edges[before * size + after]
+            }
+            Self::Sparse(edges) => edges.contains(&(before, after)),
+        }
     }
 }
 
@@ -59,57 +86,112 @@ impl MiddlePageNumber for Vec<PageNumber> {
 }
 
 trait SectionOrdering {
-    fn correctly_ordered(&self, ordering_rules: &[OrderingRule]) -> bool;
-    fn reorder(&self, ordering_rules: &OrderingRulesSet) -> Self;
+    fn correctly_ordered(&self, ordering_rules: &OrderingRulesSet) -> bool;
+    fn reorder(&self, ordering_rules: &OrderingRulesSet) -> Result<Self>
+    where
+        Self: Sized;
 }
 
 impl SectionOrdering for Section {
-    fn correctly_ordered(&self, ordering_rules: &[OrderingRule]) -> bool {
-        let mut indices = [None; 100];
-        for (idx, &page) in self.iter().enumerate() {
-            indices[page] = Some(idx);
-        }
-        ordering_rules.iter().all(|OrderingRule { before, after }| {
-            match (indices[*before], indices[*after]) {
-                (Some(idx_before), Some(idx_after)) => idx_before < idx_after,
-                _ => true,
-            }
+    fn correctly_ordered(&self, ordering_rules: &OrderingRulesSet) -> bool {
+        self.iter().enumerate().all(|(idx, &before)| {
+            self[idx + 1..]
+                .iter()
+                .all(|&after| !ordering_rules.contains(after, before))
         })
     }
 
-    fn reorder(&self, ordering_rules_set: &OrderingRulesSet) -> Self {
-        let mut reordered = self.clone();
-        reordered.sort_by(|a, b| {
-            if ordering_rules_set.contains(a, b) {
-                Ordering::Less
-            } else if ordering_rules_set.contains(b, a) {
-                Ordering::Greater
-            } else {
-                Ordering::Equal
+    /// Sorts the pages topologically by the rules that apply to them. Fails when the rules
+    /// form a cycle, or when they allow more than one order.
+    fn reorder(&self, ordering_rules: &OrderingRulesSet) -> Result<Self> {
+        if let Some(idx) = (1..self.len()).find(|&idx| self[..idx].contains(&self[idx])) {
+            bail!("Page {} appears more than once in {self:?}", self[idx]);
+        }
+
+        let mut in_degrees = self
+            .iter()
+            .map(|&page| {
+                self.iter()
+                    .filter(|&&before| ordering_rules.contains(before, page))
+                    .count()
+            })
+            .collect::<Vec<_>>();
+        let mut reordered = Vec::with_capacity(self.len());
+        while reordered.len() < self.len() {
+            let mut first = (0..self.len()).filter(|&idx| in_degrees[idx] == 0);
+            let idx = match (first.next(), first.next()) {
+                (Some(idx), None) => idx,
+                (Some(a), Some(b)) => bail!(
+                    "Pages {} and {} can be printed in any order in {self:?}",
+                    self[a],
+                    self[b]
+                ),
+                (None, _) => bail!(
+                    "Ordering rules form a cycle: {}",
+                    cycle(self, &in_degrees, ordering_rules)
+                ),
+            };
+
+            reordered.push(self[idx]);
+            in_degrees[idx] = usize::MAX;
+            for (after, in_degree) in in_degrees.iter_mut().enumerate() {
+                if *in_degree != usize::MAX && ordering_rules.contains(self[idx], self[after]) {
+                    *in_degree -= 1;
+                }
             }
-        });
-        reordered
+        }
+        Ok(reordered)
+    }
+}
+
+/// Finds a cycle among the pages that are not sorted yet, which all have a page before them,
+/// by walking back along the rules until a page repeats.
+fn cycle(
+    section: &[PageNumber],
+    in_degrees: &[usize],
+    ordering_rules: &OrderingRulesSet,
+) -> String {
+    let remaining = |idx: &usize| in_degrees[*idx] != usize::MAX;
+    let mut path = vec![(0..section.len()).find(remaining).unwrap()];
+    loop {
+        let last = *path.last().unwrap();
+        let before = (0..section.len())
+            .filter(remaining)
+            .find(|&idx| ordering_rules.contains(section[idx], section[last]))
+            .unwrap();
+        if let Some(start) = path.iter().position(|&idx| idx == before) {
+            let mut cycle = path[start..].to_vec();
+            cycle.reverse();
+            cycle.push(cycle[0]);
+            return cycle
+                .into_iter()
+                .map(|idx| section[idx].to_string())
+                .collect::<Vec<_>>()
+                .join(" -> ");
+        }
+        path.push(before);
     }
 }
 
 impl Day {
     fn part1(input: Parsed) -> Result<Output> {
+        let ordering_rules_set = OrderingRulesSet::new(&input.ordering_rules);
         Ok(input
             .sections
             .into_iter()
-            .filter(|section| section.correctly_ordered(&input.ordering_rules))
+            .filter(|section| section.correctly_ordered(&ordering_rules_set))
             .map(|section| section.middle_page_number())
             .sum())
     }
 
     fn part2(input: Parsed) -> Result<Output> {
-        let ordering_rules_set = OrderingRulesSet::from(&input.ordering_rules);
-        Ok(input
+        let ordering_rules_set = OrderingRulesSet::new(&input.ordering_rules);
+        input
             .sections
             .into_iter()
-            .filter(|section| !section.correctly_ordered(&input.ordering_rules))
-            .map(|section| section.reorder(&ordering_rules_set).middle_page_number())
-            .sum())
+            .filter(|section| !section.correctly_ordered(&ordering_rules_set))
+            .map(|section| Ok(section.reorder(&ordering_rules_set)?.middle_page_number()))
+            .sum()
     }
 }
 
@@ -133,7 +215,7 @@ impl Parser {
     }
 
     fn ordering_rule(s: &'static str) -> IResult<OrderingRule> {
-        map(separated_pair(u8, tag("|"), u8), |(before, after)| {
+        map(separated_pair(u32, tag("|"), u32), |(before, after)| {
             OrderingRule {
                 before: before as usize,
                 after: after as usize,
@@ -146,7 +228,7 @@ impl Parser {
     }
 
     fn pages(s: &'static str) -> IResult<Vec<PageNumber>> {
-        separated_list1(tag(","), map(u8, |page| page as usize))(s)
+        separated_list1(tag(","), map(u32, |page| page as usize))(s)
     }
 }
 
@@ -161,4 +243,51 @@ mod tests {
     test_example!("example1", Part1, 143);
 
     test_example!("example1", Part2, 123);
+
+    fn rules(rules: &[(PageNumber, PageNumber)]) -> OrderingRulesSet {
+        OrderingRulesSet::new(
+            &rules
+                .iter()
+                .map(|&(before, after)| OrderingRule { before, after })
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    #[test]
+    fn large_page_numbers() -> Result<()> {
+        let small = rules(&[(1, 2), (2, 3)]);
+        let large = rules(&[(1, 200), (200, 3), (3, 1_000_000)]);
+        assert!(matches!(small, OrderingRulesSet::Dense { .. }));
+        assert!(matches!(large, OrderingRulesSet::Sparse(_)));
+
+        // 200 * 100 + 3 collides with 2 * 100 + 3 in a lookup by before * 100 + after
+        assert!(!large.contains(2, 3));
+        assert!(large.contains(200, 3));
+        assert!(vec![1, 200, 3, 1_000_000].correctly_ordered(&large));
+        assert_eq!(
+            vec![1_000_000, 3, 1, 200].reorder(&large)?,
+            [1, 200, 3, 1_000_000]
+        );
+        assert_eq!(vec![3, 2, 1].reorder(&small)?, [1, 2, 3]);
+        Ok(())
+    }
+
+    #[test]
+    fn cycles_and_ambiguity() {
+        let cyclic = rules(&[(1, 2), (2, 3), (3, 4), (4, 2)]);
+        assert_eq!(
+            vec![4, 3, 2, 1].reorder(&cyclic).unwrap_err().to_string(),
+            "Ordering rules form a cycle: 2 -> 3 -> 4 -> 2"
+        );
+
+        let partial = rules(&[(1, 3), (2, 3)]);
+        assert_eq!(
+            vec![3, 2, 1].reorder(&partial).unwrap_err().to_string(),
+            "Pages 2 and 1 can be printed in any order in [3, 2, 1]"
+        );
+        assert_eq!(
+            vec![1, 2, 1].reorder(&partial).unwrap_err().to_string(),
+            "Page 1 appears more than once in [1, 2, 1]"
+        );
+    }
 }
@@ -8,7 +8,9 @@ use nom::{
     combinator::{all_consuming, map},
     multi::{many1, separated_list1},
 };
+use rayon::prelude::*;
 
+use crate::visualize::{Canvas, Cell, Rgb};
 use crate::*;
 
 day! {
@@ -24,7 +26,7 @@ enum Tile {
     Guard,
 }
 
-#[derive(PartialEq, Eq, Clone, Copy, Hash)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
 struct Location {
     x: isize,
     y: isize,
@@ -38,27 +40,170 @@ enum Direction {
     West,
 }
 
+/// The lab as a flat grid, with for every tile and direction the tile where the guard stops
+/// walking because of the next obstruction, or `None` when she walks out of the lab.
+struct Lab {
+    width: usize,
+    height: usize,
+    obstructions: Vec<bool>,
+    guard: usize,
+    jumps: Vec<[Option<usize>; 4]>,
+}
+
 impl Direction {
+    const ALL: [Direction; 4] = [
+        Direction::North,
+        Direction::East,
+        Direction::South,
+        Direction::West,
+    ];
+
+    fn rotated(self) -> Self {
+        Self::ALL[(self as usize + 1) % 4]
+    }
+
     fn rotate_clockwise(&mut self) {
-        *self = match self {
-            Direction::North => Direction::East,
-            Direction::East => Direction::South,
-            Direction::South => Direction::West,
-            Direction::West => Direction::North,
-        }
+        *self = self.rotated();
     }
 }
 
 impl Location {
-    fn next(&self, direction: Direction) -> Location {
+    fn next_n(&self, direction: Direction, n: isize) -> Location {
         let Location { x, y } = *self;
         match direction {
-            Direction::North => Location { x, y: y - 1 },
-            Direction::East => Location { x: x + 1, y },
-            Direction::South => Location { x, y: y + 1 },
-            Direction::West => Location { x: x - 1, y },
+            Direction::North => Location { x, y: y - n },
+            Direction::East => Location { x: x + n, y },
+            Direction::South => Location { x, y: y + n },
+            Direction::West => Location { x: x - n, y },
+        }
+    }
+
+    fn next(&self, direction: Direction) -> Location {
+        self.next_n(direction, 1)
+    }
+}
+
+impl Lab {
+    fn new(lines: &Parsed) -> Result<Self> {
+        let guard = Day::find_guard(lines)?;
+        let (width, height) = (lines[0].len(), lines.len());
+        let mut lab = Self {
+            width,
+            height,
+            obstructions: lines
+                .iter()
+                .flatten()
+                .map(|&tile| tile == Tile::Obstruction)
+                .collect(),
+            guard: guard.y as usize * width + guard.x as usize,
+            jumps: vec![[None; 4]; width * height],
+        };
+
+        // Every tile stops where its neighbour stops, so fill the table starting at the side
+        // the guard walks to.
+        for index in 0..width * height {
+            for direction in [Direction::North, Direction::West] {
+                lab.jumps[index][direction as usize] = lab.jump_from_neighbour(index, direction);
+            }
+        }
+        for index in (0..width * height).rev() {
+            for direction in [Direction::South, Direction::East] {
+                lab.jumps[index][direction as usize] = lab.jump_from_neighbour(index, direction);
+            }
+        }
+        Ok(lab)
+    }
+
+    fn jump_from_neighbour(&self, index: usize, direction: Direction) -> Option<usize> {
+        let next = self.step(index, direction)?;
+        if self.obstructions[next] {
+            Some(index)
+        } else {
+            self.jumps[next][direction as usize]
+        }
+    }
+
+    fn step(&self, index: usize, direction: Direction) -> Option<usize> {
+        let (x, y) = (index % self.width, index / self.width);
+        match direction {
+            Direction::North => (y > 0).then(|| index - self.width),
+            Direction::East => (x + 1 < self.width).then_some(index + 1),
+            Direction::South => (y + 1 < self.height).then_some(index + self.width),
+            Direction::West => (x > 0).then(|| index - 1),
         }
     }
+
+    fn location(&self, index: usize) -> Location {
+        Location {
+            x: (index % self.width) as isize,
+            y: (index / self.width) as isize,
+        }
+    }
+
+    /// Where the guard stops walking from `index`, with an extra obstruction placed on the
+    /// free tile `obstruction`.
+    fn jump(&self, index: usize, direction: Direction, obstruction: usize) -> Option<usize> {
+        let stop = self.jumps[index][direction as usize];
+        let (from, to) = (self.location(index), self.location(obstruction));
+        let distance = match direction {
+            Direction::North if to.x == from.x && to.y < from.y => from.y - to.y,
+            Direction::East if to.y == from.y && to.x > from.x => to.x - from.x,
+            Direction::South if to.x == from.x && to.y > from.y => to.y - from.y,
+            Direction::West if to.y == from.y && to.x < from.x => from.x - to.x,
+            _ => return stop,
+        };
+        let reach = stop.map_or(isize::MAX, |stop| {
+            let stop = self.location(stop);
+            (stop.x - from.x).abs() + (stop.y - from.y).abs()
+        });
+        if distance <= reach {
+            let before = from.next_n(direction, distance - 1);
+            Some(before.y as usize * self.width + before.x as usize)
+        } else {
+            stop
+        }
+    }
+
+    /// Whether the guard walks in a loop from `index` when the extra obstruction is placed,
+    /// which is the case as soon as she stops at the same tile in the same direction twice.
+    fn loops(&self, mut index: usize, mut direction: Direction, obstruction: usize) -> bool {
+        let mut stops = HashSet::new();
+        while let Some(stop) = self.jump(index, direction, obstruction) {
+            if !stops.insert((stop, direction)) {
+                return true;
+            }
+            index = stop;
+            direction = direction.rotated();
+        }
+        false
+    }
+
+    /// Every tile where an extra obstruction makes the guard walk in a loop. Only tiles on
+    /// her path matter, and the check for each of them starts just before she would first
+    /// walk into it.
+    fn loop_obstructions(&self) -> Vec<usize> {
+        let mut seen = vec![false; self.obstructions.len()];
+        seen[self.guard] = true;
+        let mut candidates = Vec::new();
+        let (mut index, mut direction) = (self.guard, Direction::North);
+        while let Some(next) = self.step(index, direction) {
+            if self.obstructions[next] {
+                direction = direction.rotated();
+            } else {
+                if !seen[next] {
+                    seen[next] = true;
+                    candidates.push((next, index, direction));
+                }
+                index = next;
+            }
+        }
+
+        candidates
+            .into_par_iter()
+            .filter(|&(obstruction, index, direction)| self.loops(index, direction, obstruction))
+            .map(|(obstruction, _, _)| obstruction)
+            .collect()
+    }
 }
 
 impl Day {
@@ -77,37 +222,25 @@ impl Day {
                     location = next
                 }
             }
+            visualize::try_record(|| Self::frame(&lines, &visited, location, direction));
         }
         Ok(visited.len())
     }
 
     fn part2(lines: Parsed) -> Result<Output> {
-        let mut location = Self::find_guard(&lines)?;
-        let mut direction = Direction::North;
-        let mut turns = HashSet::new();
-        let mut visited = HashSet::from([location]);
-        let mut extra_obstructions = HashSet::new();
+        let loops = Self::loop_obstructions(&lines)?;
+        visualize::try_record(|| Self::loops_frame(&lines, &loops));
+        Ok(loops.len())
+    }
 
-        loop {
-            let next = location.next(direction);
-            match Self::get(&lines, next) {
-                None => break,
-                Some(Tile::Obstruction) => {
-                    turns.insert((location, direction));
-                    direction.rotate_clockwise();
-                }
-                _ => {
-                    if !visited.contains(&next)
-                        && Self::can_place_obstruction(&lines, location, direction, &turns)
-                    {
-                        extra_obstructions.insert(next);
-                    }
-                    visited.insert(next);
-                    location = next
-                }
-            }
-        }
-        Ok(extra_obstructions.len())
+    /// The locations where an extra obstruction makes the guard walk in a loop.
+    fn loop_obstructions(lines: &Parsed) -> Result<Vec<Location>> {
+        let lab = Lab::new(lines)?;
+        Ok(lab
+            .loop_obstructions()
+            .into_iter()
+            .map(|index| lab.location(index))
+            .collect())
     }
 
     fn find_guard(lines: &Parsed) -> Result<Location> {
@@ -124,6 +257,54 @@ impl Day {
         bail!("Guard not found");
     }
 
+    fn frame(
+        lines: &Parsed,
+        visited: &HashSet<Location>,
+        guard: Location,
+        direction: Direction,
+    ) -> Result<Canvas> {
+        let mut canvas = Canvas::from_grid(lines, |tile| match tile {
+            Tile::Obstruction => Cell::new('#', Rgb::GRAY),
+            _ => Cell::new('.', Rgb::BLACK),
+        })?;
+        for location in visited {
+            canvas.set(
+                location.x as usize,
+                location.y as usize,
+                Cell::new('X', Rgb::YELLOW),
+            );
+        }
+        let symbol = match direction {
+            Direction::North => '^',
+            Direction::East => '>',
+            Direction::South => 'v',
+            Direction::West => '<',
+        };
+        canvas.set(
+            guard.x as usize,
+            guard.y as usize,
+            Cell::new(symbol, Rgb::RED),
+        );
+        Ok(canvas)
+    }
+
+    fn loops_frame(lines: &Parsed, loops: &[Location]) -> Result<Canvas> {
+        let mut canvas = Self::frame(
+            lines,
+            &HashSet::new(),
+            Self::find_guard(lines)?,
+            Direction::North,
+        )?;
+        for location in loops {
+            canvas.set(
+                location.x as usize,
+                location.y as usize,
+                Cell::new('O', Rgb::GREEN),
+            );
+        }
+        Ok(canvas)
+    }
+
     fn get(lines: &Parsed, Location { x, y }: Location) -> Option<Tile> {
         if y < 0 || y >= lines.len() as isize || x < 0 || x >= lines[0].len() as isize {
             None
@@ -131,40 +312,6 @@ impl Day {
             Some(lines[y as usize][x as usize])
         }
     }
-
-    fn can_place_obstruction(
-        lines: &Parsed,
-        location: Location,
-        direction: Direction,
-        turns: &HashSet<(Location, Direction)>,
-    ) -> bool {
-        let mut location = location;
-        let mut direction = direction;
-        let mut turns = turns.clone();
-        let obstruction = location.next(direction);
-
-        loop {
-            let next = location.next(direction);
-            if turns.contains(&(next, direction)) {
-                return true;
-            }
-            match Self::get(lines, next) {
-                None => return false,
-                Some(Tile::Obstruction) => {
-                    turns.insert((location, direction));
-                    direction.rotate_clockwise()
-                }
-                _ => {
-                    if next == obstruction {
-                        turns.insert((location, direction));
-                        direction.rotate_clockwise();
-                    } else {
-                        location = next
-                    }
-                }
-            }
-        }
-    }
 }
 
 impl Parser {
@@ -200,4 +347,77 @@ mod tests {
     test_example!("example1", Part1, 41);
 
     test_example!("example1", Part2, 6);
+
+    #[test]
+    fn loop_obstructions() -> Result<()> {
+        let lines = Parser::parse(include_str!("example1.txt"))?;
+        let mut loops = Day::loop_obstructions(&lines)?
+            .into_iter()
+            .map(|Location { x, y }| (x, y))
+            .collect::<Vec<_>>();
+        loops.sort_by_key(|&(x, y)| (y, x));
+        assert_eq!(loops, [(3, 6), (6, 7), (7, 7), (1, 8), (3, 8), (7, 9)]);
+        Ok(())
+    }
+
+    /// Walks the guard step by step, turning at the obstructions and at `extra`.
+    fn walks_in_loop(lines: &Parsed, extra: Option<Location>) -> Result<bool> {
+        let (mut location, mut direction) = (Day::find_guard(lines)?, Direction::North);
+        let mut states = HashSet::new();
+        while states.insert((location, direction)) {
+            let next = location.next(direction);
+            match Day::get(lines, next) {
+                None => return Ok(false),
+                Some(Tile::Obstruction) => direction.rotate_clockwise(),
+                _ if Some(next) == extra => direction.rotate_clockwise(),
+                _ => location = next,
+            }
+        }
+        Ok(true)
+    }
+
+    fn brute_force(lines: &Parsed) -> Result<HashSet<Location>> {
+        let mut loops = HashSet::new();
+        for y in 0..lines.len() as isize {
+            for x in 0..lines[0].len() as isize {
+                let extra = Location { x, y };
+                if Day::get(lines, extra) == Some(Tile::Empty) && walks_in_loop(lines, Some(extra))?
+                {
+                    loops.insert(extra);
+                }
+            }
+        }
+        Ok(loops)
+    }
+
+    #[test]
+    fn jump_table_matches_brute_force() -> Result<()> {
+        let mut random = Random::new(6);
+        for _ in 0..50 {
+            let (width, height) = (5 + random.below(10) as usize, 5 + random.below(10) as usize);
+            let mut lines = (0..height)
+                .map(|_| {
+                    (0..width)
+                        .map(|_| match random.below(6) {
+                            0 => Tile::Obstruction,
+                            _ => Tile::Empty,
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>();
+            lines[random.below(height as u64) as usize][random.below(width as u64) as usize] =
+                Tile::Guard;
+            // The guard has to leave the lab without an extra obstruction
+            if walks_in_loop(&lines, None)? {
+                continue;
+            }
+            let loops = Day::loop_obstructions(&lines)?;
+            assert_eq!(
+                loops.len(),
+                HashSet::<Location>::from_iter(loops.clone()).len()
+            );
+            assert_eq!(HashSet::from_iter(loops), brute_force(&lines)?);
+        }
+        Ok(())
+    }
 }
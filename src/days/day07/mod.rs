@@ -22,60 +22,194 @@ struct CalibrationEquation {
     numbers: Vec<u64>,
 }
 
-impl Day {
-    fn part1(calibration: Parsed) -> Result<Output> {
-        Ok(calibration
-            .into_iter()
-            .filter(|calibration| {
-                Self::check_part1(
-                    calibration.numbers[0],
-                    &calibration.numbers[1..],
-                    calibration.test_value,
-                )
-            })
-            .map(|calibration| calibration.test_value)
-            .sum())
+/// An operator that can also be undone, to work back from the test value.
+trait Operator: Sync {
+    fn symbol(&self) -> &'static str;
+
+    fn apply(&self, lhs: Number, rhs: Number) -> Option<Number>;
+
+    /// The left operand that gives `result` together with `rhs`, if there is exactly one.
+    fn invert(&self, result: Number, rhs: Number) -> Option<Number>;
+}
+
+struct Add;
+
+impl Operator for Add {
+    fn symbol(&self) -> &'static str {
+        "+"
     }
 
-    fn part2(calibration: Parsed) -> Result<Output> {
-        Ok(calibration
-            .into_par_iter()
-            .filter(|calibration| {
-                Self::check_part2(
-                    calibration.numbers[0],
-                    &calibration.numbers[1..],
-                    calibration.test_value,
-                )
-            })
-            .map(|calibration| calibration.test_value)
-            .sum())
+    fn apply(&self, lhs: Number, rhs: Number) -> Option<Number> {
+        lhs.checked_add(rhs)
+    }
+
+    fn invert(&self, result: Number, rhs: Number) -> Option<Number> {
+        result.checked_sub(rhs)
+    }
+}
+
+struct Multiply;
+
+impl Operator for Multiply {
+    fn symbol(&self) -> &'static str {
+        "*"
+    }
+
+    fn apply(&self, lhs: Number, rhs: Number) -> Option<Number> {
+        lhs.checked_mul(rhs)
+    }
+
+    fn invert(&self, result: Number, rhs: Number) -> Option<Number> {
+        (result.checked_rem(rhs) == Some(0)).then(|| result / rhs)
+    }
+}
+
+struct Concatenate;
+
+impl Concatenate {
+    /// The power of ten to shift the left operand by, which is one higher than the right one.
+    fn shift(rhs: Number) -> Option<Number> {
+        10u64.checked_pow(rhs.checked_ilog10().unwrap_or(0) + 1)
+    }
+}
+
+impl Operator for Concatenate {
+    fn symbol(&self) -> &'static str {
+        "||"
+    }
+
+    fn apply(&self, lhs: Number, rhs: Number) -> Option<Number> {
+        lhs.checked_mul(Self::shift(rhs)?)?.checked_add(rhs)
+    }
+
+    fn invert(&self, result: Number, rhs: Number) -> Option<Number> {
+        Self::shift(rhs)
+            .filter(|shift| result % shift == rhs)
+            .map(|shift| result / shift)
     }
+}
+
+const PART1: [&dyn Operator; 2] = [&Add, &Multiply];
+const PART2: [&dyn Operator; 3] = [&Add, &Multiply, &Concatenate];
 
-    fn check_part1(evaluated: Number, numbers: &[Number], test_value: Number) -> bool {
-        if numbers.is_empty() {
-            evaluated == test_value
-        } else if evaluated > test_value {
-            false
+impl CalibrationEquation {
+    /// The operators that make the equation true, evaluated from left to right.
+    fn solve(&self, operators: &[&'static dyn Operator]) -> Option<Vec<&'static dyn Operator>> {
+        let mut solution = Vec::with_capacity(self.numbers.len());
+        if self.numbers.contains(&0) {
+            // Multiplying by zero can't be undone, so evaluate those from the left
+            Self::solve_forwards(
+                self.numbers[0],
+                &self.numbers[1..],
+                self.test_value,
+                operators,
+                &mut solution,
+            )
+            .then_some(solution)
         } else {
-            Self::check_part1(evaluated + numbers[0], &numbers[1..], test_value)
-                || Self::check_part1(evaluated * numbers[0], &numbers[1..], test_value)
+            Self::solve_backwards(self.test_value, &self.numbers, operators, &mut solution).then(
+                || {
+                    solution.reverse();
+                    solution
+                },
+            )
         }
     }
 
-    fn check_part2(evaluated: Number, numbers: &[Number], test_value: Number) -> bool {
-        if numbers.is_empty() {
-            evaluated == test_value
-        } else if evaluated > test_value {
-            false
-        } else {
-            Self::check_part2(evaluated + numbers[0], &numbers[1..], test_value)
-                || Self::check_part2(evaluated * numbers[0], &numbers[1..], test_value)
-                || Self::check_part2(
-                    format!("{}{}", evaluated, numbers[0]).parse().unwrap(),
-                    &numbers[1..],
-                    test_value,
-                )
+    /// Undoes the last operation until only the first number is left, which then has to be
+    /// the result. Values that can't be undone are pruned right away.
+    fn solve_backwards(
+        result: Number,
+        numbers: &[Number],
+        operators: &[&'static dyn Operator],
+        solution: &mut Vec<&'static dyn Operator>,
+    ) -> bool {
+        match numbers {
+            [first] => *first == result,
+            [rest @ .., last] => operators.iter().any(|&operator| {
+                let Some(lhs) = operator.invert(result, *last) else {
+                    return false;
+                };
+                solution.push(operator);
+                let solved = Self::solve_backwards(lhs, rest, operators, solution);
+                if !solved {
+                    solution.pop();
+                }
+                solved
+            }),
+            [] => false,
+        }
+    }
+
+    fn solve_forwards(
+        evaluated: Number,
+        numbers: &[Number],
+        test_value: Number,
+        operators: &[&'static dyn Operator],
+        solution: &mut Vec<&'static dyn Operator>,
+    ) -> bool {
+        match numbers {
+            [] => evaluated == test_value,
+            [next, rest @ ..] => operators.iter().any(|&operator| {
+                let Some(evaluated) = operator.apply(evaluated, *next) else {
+                    return false;
+                };
+                solution.push(operator);
+                let solved = Self::solve_forwards(evaluated, rest, test_value, operators, solution);
+                if !solved {
+                    solution.pop();
+                }
+                solved
+            }),
+        }
+    }
+
+    fn equation(&self, operators: &[&dyn Operator]) -> String {
+        let mut equation = format!("{} = {}", self.test_value, self.numbers[0]);
+        for (operator, number) in operators.iter().zip(&self.numbers[1..]) {
+            equation += &format!(" {} {number}", operator.symbol());
         }
+        equation
+    }
+}
+
+impl Day {
+    pub fn explain(part: Part) -> Result<String> {
+        Self::explanation(Parser::parse(include_str!("input.txt"))?, part)
+    }
+
+    fn part1(calibration: Parsed) -> Result<Output> {
+        Ok(Self::total_calibration_result(calibration, &PART1))
+    }
+
+    fn part2(calibration: Parsed) -> Result<Output> {
+        Ok(Self::total_calibration_result(calibration, &PART2))
+    }
+
+    fn total_calibration_result(
+        calibration: Parsed,
+        operators: &[&'static dyn Operator],
+    ) -> Output {
+        calibration
+            .into_par_iter()
+            .filter(|equation| equation.solve(operators).is_some())
+            .map(|equation| equation.test_value)
+            .sum()
+    }
+
+    /// Every equation that can be made true, with its operators.
+    fn explanation(calibration: Parsed, part: Part) -> Result<String> {
+        let operators = match part {
+            Part1 => &PART1[..],
+            Part2 => &PART2[..],
+        };
+        Ok(calibration
+            .iter()
+            .filter_map(|equation| {
+                let solution = equation.solve(operators)?;
+                Some(equation.equation(&solution) + "\n")
+            })
+            .collect())
     }
 }
 
@@ -114,4 +248,59 @@ mod tests {
     test_example!("example1", Part1, 3749);
 
     test_example!("example1", Part2, 11387);
+
+    #[test]
+    fn operators() {
+        for operator in PART2 {
+            for (lhs, rhs) in [(0, 5), (12, 345), (7, 10), (1, 1)] {
+                let result = operator.apply(lhs, rhs).unwrap();
+                assert_eq!(operator.invert(result, rhs), Some(lhs));
+            }
+        }
+        assert_eq!(Concatenate.apply(12, 345), Some(12345));
+        assert_eq!(Concatenate.invert(12345, 45), Some(123));
+        assert_eq!(Concatenate.invert(12345, 44), None);
+        assert_eq!(Concatenate.apply(Number::MAX / 10, 99), None);
+        assert_eq!(Multiply.apply(Number::MAX, 2), None);
+        assert_eq!(Multiply.invert(10, 3), None);
+        assert_eq!(Multiply.invert(0, 0), None);
+        assert_eq!(Concatenate.apply(6, 0), Some(60));
+        assert_eq!(Concatenate.invert(60, 0), Some(6));
+        assert_eq!(Add.invert(3, 10), None);
+    }
+
+    #[test]
+    fn explanation() -> Result<()> {
+        let calibration = Parser::parse(include_str!("example1.txt"))?;
+        assert_eq!(
+            Day::explanation(calibration, Part2)?,
+            "190 = 10 * 19\n\
+             3267 = 81 * 40 + 27\n\
+             156 = 15 || 6\n\
+             7290 = 6 * 8 || 6 * 15\n\
+             192 = 17 || 8 + 14\n\
+             292 = 11 + 6 * 16 + 20\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn zeros_and_overflow() {
+        let equation = |test_value, numbers: &[Number]| CalibrationEquation {
+            test_value,
+            numbers: numbers.to_vec(),
+        };
+        let zero = equation(7, &[5, 0, 7]);
+        let solution = zero.solve(&PART1).unwrap();
+        assert_eq!(zero.equation(&solution), "7 = 5 * 0 + 7");
+        assert!(equation(8, &[5, 0, 7]).solve(&PART1).is_none());
+
+        let large = equation(Number::MAX, &[Number::MAX, 1, 1]);
+        let solution = large.solve(&PART2).unwrap();
+        assert_eq!(
+            large.equation(&solution),
+            format!("{} = {} * 1 * 1", Number::MAX, Number::MAX)
+        );
+        assert!(equation(1, &[Number::MAX, 99, 1]).solve(&PART2).is_none());
+    }
 }
@@ -1,6 +1,9 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap};
+
+use num_integer::Integer;
 
 use crate::*;
+use visualize::{Canvas, Cell, Frame, Rgb};
 
 day! {
     Output = usize,
@@ -13,82 +16,150 @@ struct Map {
     antennas: HashMap<Antenna, Vec<Location>>,
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 struct Antenna(char);
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 struct Location {
     x: isize,
     y: isize,
 }
 
+/// Two antennas of the same frequency that create an antinode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct Pair {
+    antenna: Antenna,
+    a: Location,
+    b: Location,
+}
+
+enum Harmonics {
+    /// Antinodes at these multiples of the distance between the antennas beyond either
+    /// antenna. Part 1 is `[1]`, where one antenna is twice as far away as the other.
+    Multiples(Vec<isize>),
+    /// Antinodes at every position in line with the antennas, also between them.
+    Collinear,
+}
+
+impl Location {
+    fn offset(self, dx: isize, dy: isize, n: isize) -> Location {
+        Location {
+            x: self.x + n * dx,
+            y: self.y + n * dy,
+        }
+    }
+}
+
 impl Map {
     fn contains(&self, location: Location) -> bool {
         location.x >= 0 && location.x < self.width && location.y >= 0 && location.y < self.height
     }
-}
 
-impl Day {
-    fn part1(map: Parsed) -> Result<Output> {
-        let mut antinodes = HashSet::new();
-        for locations in map.antennas.values() {
-            for (idx, a) in locations.iter().enumerate() {
-                for b in &locations[idx + 1..] {
-                    let antinode1 = Self::next_antinode(*a, *b);
-                    if map.contains(antinode1) {
-                        antinodes.insert(antinode1);
-                    }
-
-                    let antinode2 = Self::next_antinode(*b, *a);
-                    if map.contains(antinode2) {
-                        antinodes.insert(antinode2);
+    /// Every antinode on the map with the pairs of antennas that create it.
+    fn antinodes(&self, harmonics: &Harmonics) -> BTreeMap<Location, Vec<Pair>> {
+        let mut antinodes = BTreeMap::<_, Vec<_>>::new();
+        for (&antenna, locations) in &self.antennas {
+            for (idx, &a) in locations.iter().enumerate() {
+                for &b in &locations[idx + 1..] {
+                    let pair = Pair { antenna, a, b };
+                    for antinode in self.pair_antinodes(a, b, harmonics) {
+                        antinodes.entry(antinode).or_default().push(pair);
                     }
                 }
             }
         }
-
-        Ok(antinodes.len())
+        for pairs in antinodes.values_mut() {
+            pairs.sort_unstable();
+            pairs.dedup();
+        }
+        antinodes
     }
 
-    fn part2(map: Parsed) -> Result<Output> {
-        let mut antinodes = HashSet::new();
-        for locations in map.antennas.values() {
-            for (idx, a) in locations.iter().enumerate() {
-                for b in &locations[idx + 1..] {
-                    antinodes.insert(*a);
-                    antinodes.insert(*b);
-
-                    let mut k = *a;
-                    let mut l = *b;
-                    let mut m = Self::next_antinode(k, l);
-                    while map.contains(m) {
-                        antinodes.insert(m);
-                        k = l;
-                        l = m;
-                        m = Self::next_antinode(k, l);
-                    }
+    fn pair_antinodes(&self, a: Location, b: Location, harmonics: &Harmonics) -> Vec<Location> {
+        let (dx, dy) = (b.x - a.x, b.y - a.y);
+        match harmonics {
+            Harmonics::Multiples(multiples) => multiples
+                .iter()
+                .flat_map(|&n| [b.offset(dx, dy, n), a.offset(dx, dy, -n)])
+                .filter(|&antinode| self.contains(antinode))
+                .collect(),
+            Harmonics::Collinear => {
+                // The smallest step between grid positions on the line
+                let gcd = dx.gcd(&dy);
+                let (dx, dy) = (dx / gcd, dy / gcd);
+                let forward = (0..).map(|n| a.offset(dx, dy, n));
+                let backward = (1..).map(|n| a.offset(dx, dy, -n));
+                forward
+                    .take_while(|&antinode| self.contains(antinode))
+                    .chain(backward.take_while(|&antinode| self.contains(antinode)))
+                    .collect()
+            }
+        }
+    }
 
-                    k = *b;
-                    l = *a;
-                    let mut m = Self::next_antinode(k, l);
-                    while map.contains(m) {
-                        antinodes.insert(m);
-                        k = l;
-                        l = m;
-                        m = Self::next_antinode(k, l);
-                    }
-                }
+    fn canvas(&self, antinodes: &BTreeMap<Location, Vec<Pair>>) -> Canvas {
+        let mut canvas = Canvas::new(
+            self.width as usize,
+            self.height as usize,
+            Cell::new('.', Rgb::BLACK),
+        );
+        for location in antinodes.keys() {
+            canvas.set(
+                location.x as usize,
+                location.y as usize,
+                Cell::new('#', Rgb::YELLOW),
+            );
+        }
+        for (&Antenna(frequency), locations) in &self.antennas {
+            for location in locations {
+                canvas.set(
+                    location.x as usize,
+                    location.y as usize,
+                    Cell::new(frequency, Rgb::GREEN),
+                );
             }
         }
+        canvas
+    }
+}
 
-        Ok(antinodes.len())
+impl Day {
+    pub fn explain(part: Part) -> Result<String> {
+        Ok(Self::explanation(
+            &Parser::parse(include_str!("input.txt"))?,
+            part,
+        ))
     }
 
-    fn next_antinode(a: Location, b: Location) -> Location {
-        Location {
-            x: 2 * b.x - a.x,
-            y: 2 * b.y - a.y,
+    fn part1(map: Parsed) -> Result<Output> {
+        Ok(map.antinodes(&Self::harmonics(Part1)).len())
+    }
+
+    fn part2(map: Parsed) -> Result<Output> {
+        Ok(map.antinodes(&Self::harmonics(Part2)).len())
+    }
+
+    fn harmonics(part: Part) -> Harmonics {
+        match part {
+            Part1 => Harmonics::Multiples(vec![1]),
+            Part2 => Harmonics::Collinear,
+        }
+    }
+
+    fn explanation(map: &Map, part: Part) -> String {
+        let antinodes = map.antinodes(&Self::harmonics(part));
+        let mut explanation = map.canvas(&antinodes).to_ascii();
+        for (antinode, pairs) in &antinodes {
+            let pairs = pairs
+                .iter()
+                .map(|Pair { antenna, a, b }| {
+                    format!("{} {},{} and {},{}", antenna.0, a.x, a.y, b.x, b.y)
+                })
+                .collect::<Vec<_>>()
+                .join("; ");
+            explanation += &format!("{},{}: {pairs}\n", antinode.x, antinode.y);
         }
+        explanation + &format!("{} antinodes\n", antinodes.len())
     }
 }
 
@@ -134,4 +205,63 @@ mod tests {
     test_example!("example1", Part2, 34);
 
     test_example!("example2", Part2, 9);
+
+    #[test]
+    fn explanation() -> Result<()> {
+        let map = Parser::parse(include_str!("example1.txt"))?;
+        let explanation = Day::explanation(&map, Part1);
+        let lines = explanation.lines().collect::<Vec<_>>();
+        assert_eq!(
+            lines[..12],
+            [
+                "......#....#",
+                "...#....0...",
+                "....#0....#.",
+                "..#....0....",
+                "....0....#..",
+                ".#....A.....",
+                "...#........",
+                "#......#....",
+                "........A...",
+                ".........A..",
+                "..........#.",
+                "..........#.",
+            ]
+        );
+        assert_eq!(lines[12], "0,7: 0 8,1 and 4,4");
+        assert!(lines.contains(&"3,1: 0 5,2 and 7,3; A 6,5 and 9,9"));
+        assert_eq!(lines[lines.len() - 1], "14 antinodes");
+        Ok(())
+    }
+
+    #[test]
+    fn harmonics() -> Result<()> {
+        let map = Parser::parse("a........\n.........\n.........\n......a..")?;
+        let antinodes = |harmonics| {
+            map.antinodes(&harmonics)
+                .into_iter()
+                .map(|(Location { x, y }, pairs)| ((x, y), pairs))
+                .collect::<Vec<_>>()
+        };
+        let pair = Pair {
+            antenna: Antenna('a'),
+            a: Location { x: 0, y: 0 },
+            b: Location { x: 6, y: 3 },
+        };
+        assert_eq!(antinodes(Harmonics::Multiples(vec![1])), []);
+        assert_eq!(
+            antinodes(Harmonics::Multiples(vec![0, -1])),
+            [((0, 0), vec![pair]), ((6, 3), vec![pair])]
+        );
+        assert_eq!(
+            antinodes(Harmonics::Collinear),
+            [
+                ((0, 0), vec![pair]),
+                ((2, 1), vec![pair]),
+                ((4, 2), vec![pair]),
+                ((6, 3), vec![pair])
+            ]
+        );
+        Ok(())
+    }
 }
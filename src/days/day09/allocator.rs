@@ -0,0 +1,155 @@
+use std::{cmp::Reverse, collections::BinaryHeap, fmt};
+
+use super::{DiskMap, Span, MAX_LENGTH};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Strategy {
+    /// Move single blocks from the end of the disk to the leftmost free block
+    Blocks,
+    /// Move whole files to the leftmost span of free space that fits
+    FirstFit,
+    /// Move whole files to the smallest span of free space that fits, the leftmost one of
+    /// those if there are several
+    BestFit,
+}
+
+/// Where the blocks of every file end up after compacting the disk.
+pub struct Layout {
+    size: usize,
+    files: Vec<Vec<Span>>,
+}
+
+impl Layout {
+    pub fn checksum(&self) -> usize {
+        self.files
+            .iter()
+            .enumerate()
+            .flat_map(|(id, spans)| {
+                spans.iter().map(move |span| {
+                    id * (span.length * span.start
+                        + span.length * span.length.saturating_sub(1) / 2)
+                })
+            })
+            .sum()
+    }
+}
+
+impl fmt::Display for Layout {
+    /// Like the puzzle text, every block shows the last digit of the id of its file.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut blocks = vec!['.'; self.size];
+        for (id, spans) in self.files.iter().enumerate() {
+            let digit = char::from_digit((id % 10) as u32, 10).unwrap();
+            for span in spans {
+                blocks[span.start..span.start + span.length].fill(digit);
+            }
+        }
+        write!(f, "{}", String::from_iter(blocks))
+    }
+}
+
+impl DiskMap {
+    pub fn compact(&self, strategy: Strategy) -> Layout {
+        Layout {
+            size: self.size,
+            files: match strategy {
+                Strategy::Blocks => self.move_blocks(),
+                Strategy::FirstFit | Strategy::BestFit => self.move_files(strategy),
+            },
+        }
+    }
+
+    fn move_blocks(&self) -> Vec<Vec<Span>> {
+        let mut free = self.free.iter().copied().filter(|span| span.length > 0);
+        let mut hole = free.next();
+        self.files
+            .iter()
+            .rev()
+            .map(|&file| {
+                let mut remaining = file;
+                let mut spans = Vec::new();
+                while remaining.length > 0 {
+                    let Some(span) = hole.as_mut().filter(|span| span.start < remaining.start)
+                    else {
+                        break;
+                    };
+                    let length = span.length.min(remaining.length);
+                    spans.push(Span {
+                        start: span.start,
+                        length,
+                    });
+                    span.start += length;
+                    span.length -= length;
+                    remaining.length -= length;
+                    if span.length == 0 {
+                        hole = free.next();
+                    }
+                }
+                if remaining.length > 0 {
+                    spans.push(remaining);
+                }
+                spans
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+            .rev()
+            .collect()
+    }
+
+    /// Tries to move every file once, starting with the highest id. Space that a file leaves
+    /// behind is never used, because all files with a lower id are to the left of it.
+    fn move_files(&self, strategy: Strategy) -> Vec<Vec<Span>> {
+        let mut free = FreeSpans::new(&self.free);
+        let mut files = self
+            .files
+            .iter()
+            .map(|&file| vec![file])
+            .collect::<Vec<_>>();
+        for spans in files.iter_mut().rev() {
+            let file = &mut spans[0];
+            if file.length == 0 {
+                continue;
+            }
+            if let Some(start) = free.take(file.length, file.start, strategy) {
+                file.start = start;
+            }
+        }
+        files
+    }
+}
+
+/// The starts of the spans of free space, in a min-heap per length.
+struct FreeSpans {
+    heaps: [BinaryHeap<Reverse<usize>>; MAX_LENGTH + 1],
+}
+
+impl FreeSpans {
+    fn new(spans: &[Span]) -> Self {
+        let mut free = Self {
+            heaps: Default::default(),
+        };
+        for span in spans.iter().filter(|span| span.length > 0) {
+            free.heaps[span.length].push(Reverse(span.start));
+        }
+        free
+    }
+
+    /// Takes `length` blocks from a span of free space that starts before `before`, and
+    /// returns where they start.
+    fn take(&mut self, length: usize, before: usize, strategy: Strategy) -> Option<usize> {
+        let mut candidates = (length..=MAX_LENGTH).filter_map(|span_length| {
+            let &Reverse(start) = self.heaps[span_length].peek()?;
+            (start < before).then_some((span_length, start))
+        });
+        let (span_length, start) = match strategy {
+            Strategy::BestFit => candidates.next()?,
+            _ => candidates.min_by_key(|&(_, start)| start)?,
+        };
+
+        self.heaps[span_length].pop();
+        if span_length > length {
+            self.heaps[span_length - length].push(Reverse(start + length));
+        }
+        Some(start)
+    }
+}
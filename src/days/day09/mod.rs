@@ -1,10 +1,9 @@
-use std::{
-    iter::{repeat_n, Flatten, RepeatN},
-    ops::{Index, IndexMut},
-    slice::SliceIndex,
-};
+mod allocator;
+
+use anyhow::anyhow;
 
 use crate::*;
+pub use allocator::Strategy;
 
 day! {
     Output = usize,
@@ -12,151 +11,65 @@ day! {
     bench_sample_size: 80,
 }
 
+/// The longest span a digit of the disk map can describe.
+const MAX_LENGTH: usize = 9;
+
 struct DiskMap {
-    contents: Vec<DiskContent>,
+    size: usize,
+    files: Vec<Span>,
+    free: Vec<Span>,
 }
 
-struct DiskContent {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Span {
+    start: usize,
     length: usize,
-    content: DiskBlock,
-}
-
-#[derive(Clone, Copy)]
-enum DiskBlock {
-    File { id: usize },
-    FreeSpace,
-}
-
-impl<Idx> Index<Idx> for DiskMap
-where
-    Idx: SliceIndex<[DiskContent]>,
-{
-    type Output = <Idx as SliceIndex<[DiskContent]>>::Output;
-
-    fn index(&self, index: Idx) -> &Self::Output {
-        self.contents.index(index)
-    }
 }
 
-impl<Idx> IndexMut<Idx> for DiskMap
-where
-    Idx: SliceIndex<[DiskContent]>,
-{
-    fn index_mut(&mut self, index: Idx) -> &mut Self::Output {
-        self.contents.index_mut(index)
-    }
-}
-
-impl DiskMap {
-    fn len(&self) -> usize {
-        self.contents.len()
-    }
-}
-
-impl IntoIterator for DiskMap {
-    type Item = DiskBlock;
-    type IntoIter = Flatten<std::vec::IntoIter<DiskContent>>;
-
-    fn into_iter(self) -> Self::IntoIter {
-        self.contents.into_iter().flatten()
-    }
-}
-
-impl IntoIterator for DiskContent {
-    type Item = DiskBlock;
-    type IntoIter = RepeatN<Self::Item>;
-
-    fn into_iter(self) -> Self::IntoIter {
-        repeat_n(self.content, self.length)
+impl Day {
+    pub fn compact(strategy: Strategy, show_layout: bool) -> Result<String> {
+        let layout = Parser::parse(include_str!("input.txt"))?.compact(strategy);
+        let checksum = format!("Checksum {}\n", layout.checksum());
+        Ok(if show_layout {
+            format!("{layout}\n{checksum}")
+        } else {
+            checksum
+        })
     }
-}
 
-impl Day {
     fn part1(disk_map: Parsed) -> Result<Output> {
-        let disk_blocks = Vec::from_iter(disk_map);
-        let mut checksum = 0;
-        let mut idx_checksum = 0;
-        let mut idx_front = 0;
-        let mut idx_back = disk_blocks.len() - 1;
-        while idx_front <= idx_back {
-            match disk_blocks[idx_front] {
-                DiskBlock::File { id } => {
-                    idx_front += 1;
-                    checksum += idx_checksum * id;
-                    idx_checksum += 1
-                }
-                DiskBlock::FreeSpace => {
-                    idx_front += 1;
-                    while idx_front <= idx_back {
-                        match disk_blocks[idx_back] {
-                            DiskBlock::File { id } => {
-                                idx_back -= 1;
-                                checksum += idx_checksum * id;
-                                idx_checksum += 1;
-                                break;
-                            }
-                            DiskBlock::FreeSpace => {
-                                idx_back -= 1;
-                            }
-                        }
-                    }
-                }
-            }
-        }
-
-        Ok(checksum)
+        Ok(disk_map.compact(Strategy::Blocks).checksum())
     }
 
-    fn part2(mut disk_map: Parsed) -> Result<Output> {
-        let mut idx_file = disk_map.len() - 1;
-        while idx_file > 0 {
-            let mut idx_free_space = 1;
-            while idx_free_space < idx_file {
-                if disk_map[idx_free_space].length >= disk_map[idx_file].length {
-                    disk_map[idx_free_space..=idx_file].rotate_right(2);
-
-                    if idx_file + 1 < disk_map.len() {
-                        disk_map[idx_file + 1].length +=
-                            disk_map[idx_free_space].length + disk_map[idx_free_space + 1].length;
-                    }
-                    disk_map[idx_free_space + 2].length -= disk_map[idx_free_space + 1].length;
-                    disk_map[idx_free_space].length = 0;
-
-                    idx_file += 2;
-                    break;
-                }
-                idx_free_space += 2;
-            }
-            idx_file -= 2;
-        }
-
-        Ok(disk_map
-            .into_iter()
-            .enumerate()
-            .map(|(idx, disk_block)| match disk_block {
-                DiskBlock::File { id } => idx * id,
-                DiskBlock::FreeSpace => 0,
-            })
-            .sum())
+    fn part2(disk_map: Parsed) -> Result<Output> {
+        Ok(disk_map.compact(Strategy::FirstFit).checksum())
     }
 }
 
 impl Parser {
     fn parse(input: &'static str) -> Result<Parsed> {
-        Ok(DiskMap {
-            contents: input
-                .bytes()
-                .enumerate()
-                .map(|(idx, b)| DiskContent {
-                    length: b as usize - 48,
-                    content: if idx % 2 == 0 {
-                        DiskBlock::File { id: idx / 2 }
-                    } else {
-                        DiskBlock::FreeSpace
-                    },
-                })
-                .collect(),
-        })
+        let mut disk_map = DiskMap {
+            size: 0,
+            files: Vec::new(),
+            free: Vec::new(),
+        };
+        for (idx, c) in input.trim_end().chars().enumerate() {
+            let length = c
+                .to_digit(10)
+                .ok_or_else(|| anyhow!("Invalid length '{c}' in disk map"))?
+                as usize;
+            let span = Span {
+                start: disk_map.size,
+                length,
+            };
+            if idx & 1 == 0 {
+                disk_map.files.push(span);
+            } else {
+                disk_map.free.push(span);
+            }
+            disk_map.size += length;
+        }
+        Ok(disk_map)
     }
 }
 
@@ -171,4 +84,40 @@ mod tests {
     test_example!("example1", Part1, 1928);
 
     test_example!("example1", Part2, 2858);
+
+    fn layout(disk_map: &str, strategy: Strategy) -> Result<String> {
+        Ok(Parser::parse(disk_map.to_string().leak())?
+            .compact(strategy)
+            .to_string())
+    }
+
+    #[test]
+    fn layouts() -> Result<()> {
+        let example = include_str!("example1.txt");
+        assert_eq!(
+            layout(example, Strategy::Blocks)?,
+            "0099811188827773336446555566.............."
+        );
+        assert_eq!(
+            layout(example, Strategy::FirstFit)?,
+            "00992111777.44.333....5555.6666.....8888.."
+        );
+        assert_eq!(layout("12345", Strategy::Blocks)?, "022111222......");
+        assert_eq!(layout("12345", Strategy::FirstFit)?, "0..111....22222");
+        Ok(())
+    }
+
+    #[test]
+    fn best_fit() -> Result<()> {
+        assert_eq!(layout("23122", Strategy::FirstFit)?, "00221.....");
+        assert_eq!(layout("23122", Strategy::BestFit)?, "001...22..");
+        assert_eq!(
+            Parser::parse("23122")?
+                .compact(Strategy::BestFit)
+                .checksum(),
+            2 + 2 * (6 + 7)
+        );
+        assert!(Parser::parse("12a").is_err());
+        Ok(())
+    }
 }
@@ -1,120 +1,265 @@
-use std::collections::BTreeSet;
+use anyhow::{anyhow, bail};
 
 use crate::*;
 
 day! {
     Output = usize,
-    Parsed = Vec<Vec<u8>>,
+    Parsed = Map,
 }
 
-impl Day {
-    fn part1(lines: Parsed) -> Result<Output> {
-        let mut sum = 0;
-        for (y, line) in lines.iter().enumerate() {
-            for (x, &height) in line.iter().enumerate() {
-                if height == 0 {
-                    let mut trail_ends = BTreeSet::new();
-                    Self::trail_score(&lines, x as isize, y as isize, 0, &mut trail_ends);
-                    sum += trail_ends.len();
-                }
-            }
+const TRAILHEAD: u8 = 0;
+const SUMMIT: u8 = 9;
+const IMPASSABLE: u8 = u8::MAX;
+
+const ORTHOGONAL: [(isize, isize); 4] = [(0, -1), (1, 0), (0, 1), (-1, 0)];
+const ALL_DIRECTIONS: [(isize, isize); 8] = [
+    (0, -1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+    (0, 1),
+    (-1, 1),
+    (-1, 0),
+    (-1, -1),
+];
+
+/// Which steps a hiking trail can take. Trails always go up, so `max_climb` is at least 1.
+#[derive(Clone, Copy)]
+pub struct Rules {
+    pub max_climb: u8,
+    pub diagonal: bool,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Self {
+            max_climb: 1,
+            diagonal: false,
         }
-        Ok(sum)
     }
+}
 
-    fn part2(lines: Parsed) -> Result<Output> {
-        let mut sum = 0;
-        for (y, line) in lines.iter().enumerate() {
-            for (x, &height) in line.iter().enumerate() {
-                if height == 0 {
-                    sum += Self::trail_rating(&lines, x as isize, y as isize, 0);
-                }
-            }
+impl Rules {
+    fn directions(&self) -> &'static [(isize, isize)] {
+        if self.diagonal {
+            &ALL_DIRECTIONS
+        } else {
+            &ORTHOGONAL
         }
-        Ok(sum)
     }
 
-    fn trail_score(
-        lines: &Parsed,
-        x: isize,
-        y: isize,
-        height: u8,
-        trail_ends: &mut BTreeSet<(isize, isize)>,
-    ) {
-        if height == 9 {
-            trail_ends.insert((x, y));
-        } else {
-            if let Some(h) = Self::get(lines, x, y - 1) {
-                if h == height + 1 {
-                    Self::trail_score(lines, x, y - 1, height + 1, trail_ends);
-                }
-            }
-            if let Some(h) = Self::get(lines, x, y + 1) {
-                if h == height + 1 {
-                    Self::trail_score(lines, x, y + 1, height + 1, trail_ends);
-                }
+    fn allows(&self, from: u8, to: u8) -> bool {
+        to <= SUMMIT && to > from && to - from <= self.max_climb
+    }
+}
+
+struct Map {
+    width: usize,
+    heights: Vec<u8>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Trailhead {
+    position: (usize, usize),
+    score: usize,
+    rating: usize,
+}
+
+/// For every position the number of summits it leads to, and the number of trails to them.
+struct Analysis {
+    rules: Rules,
+    scores: Vec<usize>,
+    ratings: Vec<usize>,
+}
+
+impl Map {
+    fn adjacent(&self, index: usize, rules: Rules) -> impl Iterator<Item = usize> + '_ {
+        let (x, y) = ((index % self.width) as isize, (index / self.width) as isize);
+        let height = self.heights.len() / self.width;
+        rules.directions().iter().filter_map(move |&(dx, dy)| {
+            let (x, y) = (x + dx, y + dy);
+            (x >= 0 && y >= 0 && (x as usize) < self.width && (y as usize) < height)
+                .then(|| y as usize * self.width + x as usize)
+        })
+    }
+
+    /// The positions a trail can step to from `index`.
+    fn neighbours(&self, index: usize, rules: Rules) -> impl Iterator<Item = usize> + '_ {
+        self.adjacent(index, rules)
+            .filter(move |&next| rules.allows(self.heights[index], self.heights[next]))
+    }
+
+    /// The positions a trail can step from to reach `index`.
+    fn predecessors(&self, index: usize, rules: Rules) -> impl Iterator<Item = usize> + '_ {
+        self.adjacent(index, rules)
+            .filter(move |&previous| rules.allows(self.heights[previous], self.heights[index]))
+    }
+
+    /// Visits the positions from the summits down to the trailheads, so every position only
+    /// has to add up the ratings of the positions one step higher. Every summit then walks
+    /// down once to add itself to the scores of the positions that lead to it.
+    fn analyse(&self, rules: Rules) -> Analysis {
+        let mut levels = vec![Vec::new(); SUMMIT as usize + 1];
+        for (index, &height) in self.heights.iter().enumerate() {
+            if height <= SUMMIT {
+                levels[height as usize].push(index);
             }
-            if let Some(h) = Self::get(lines, x - 1, y) {
-                if h == height + 1 {
-                    Self::trail_score(lines, x - 1, y, height + 1, trail_ends);
+        }
+
+        let mut analysis = Analysis {
+            rules,
+            scores: vec![0; self.heights.len()],
+            ratings: vec![0; self.heights.len()],
+        };
+        for &index in &levels[SUMMIT as usize] {
+            analysis.ratings[index] = 1;
+        }
+        for level in levels[..SUMMIT as usize].iter().rev() {
+            for &index in level {
+                for next in self.neighbours(index, rules) {
+                    analysis.ratings[index] += analysis.ratings[next];
                 }
             }
-            if let Some(h) = Self::get(lines, x + 1, y) {
-                if h == height + 1 {
-                    Self::trail_score(lines, x + 1, y, height + 1, trail_ends);
+        }
+
+        // The last summit that reached each position, so it is only counted once
+        let mut reached_by = vec![usize::MAX; self.heights.len()];
+        for &summit in &levels[SUMMIT as usize] {
+            reached_by[summit] = summit;
+            let mut stack = vec![summit];
+            while let Some(index) = stack.pop() {
+                analysis.scores[index] += 1;
+                for previous in self.predecessors(index, rules) {
+                    if reached_by[previous] != summit {
+                        reached_by[previous] = summit;
+                        stack.push(previous);
+                    }
                 }
             }
         }
+        analysis
     }
 
-    fn trail_rating(lines: &Parsed, x: isize, y: isize, height: u8) -> usize {
-        if height == 9 {
-            1
-        } else {
-            let mut score = 0;
-            if let Some(h) = Self::get(lines, x, y - 1) {
-                if h == height + 1 {
-                    score += Self::trail_rating(lines, x, y - 1, height + 1);
-                }
-            }
-            if let Some(h) = Self::get(lines, x, y + 1) {
-                if h == height + 1 {
-                    score += Self::trail_rating(lines, x, y + 1, height + 1);
-                }
+    fn trailheads(&self, analysis: &Analysis) -> Vec<Trailhead> {
+        self.heights
+            .iter()
+            .enumerate()
+            .filter(|&(_, &height)| height == TRAILHEAD)
+            .map(|(index, _)| Trailhead {
+                position: (index % self.width, index / self.width),
+                score: analysis.scores[index],
+                rating: analysis.ratings[index],
+            })
+            .collect()
+    }
+
+    /// Lists the trails from a trailhead, at most `limit` of them.
+    fn trails(&self, analysis: &Analysis, (x, y): (usize, usize), limit: usize) -> Vec<Vec<usize>> {
+        let start = y * self.width + x;
+        let mut trails = Vec::new();
+        let mut trail = vec![start];
+        self.trails_from(analysis, limit, &mut trail, &mut trails);
+        trails
+    }
+
+    fn trails_from(
+        &self,
+        analysis: &Analysis,
+        limit: usize,
+        trail: &mut Vec<usize>,
+        trails: &mut Vec<Vec<usize>>,
+    ) {
+        let index = trail[trail.len() - 1];
+        if self.heights[index] == SUMMIT {
+            trails.push(trail.clone());
+            return;
+        }
+        for next in self.neighbours(index, analysis.rules) {
+            if trails.len() >= limit {
+                return;
             }
-            if let Some(h) = Self::get(lines, x - 1, y) {
-                if h == height + 1 {
-                    score += Self::trail_rating(lines, x - 1, y, height + 1);
-                }
+            // Skip positions from which no summit can be reached
+            if analysis.ratings[next] > 0 {
+                trail.push(next);
+                self.trails_from(analysis, limit, trail, trails);
+                trail.pop();
             }
-            if let Some(h) = Self::get(lines, x + 1, y) {
-                if h == height + 1 {
-                    score += Self::trail_rating(lines, x + 1, y, height + 1);
-                }
-            }
-            score
         }
     }
+}
 
-    fn get(lines: &Parsed, x: isize, y: isize) -> Option<u8> {
-        if y < 0 || y >= lines.len() as isize || x < 0 || x >= lines[0].len() as isize {
-            None
-        } else {
-            Some(lines[y as usize][x as usize])
-        }
+impl Day {
+    /// The rating of the trailhead at `x,y` of your input, and at most `limit` of its trails.
+    pub fn trails(x: usize, y: usize, rules: Rules, limit: usize) -> Result<(usize, Vec<String>)> {
+        let map = Parser::parse(include_str!("input.txt"))?;
+        Self::list_trails(&map, (x, y), rules, limit)
+    }
+
+    fn part1(map: Parsed) -> Result<Output> {
+        Ok(map
+            .trailheads(&map.analyse(Rules::default()))
+            .iter()
+            .map(|trailhead| trailhead.score)
+            .sum())
+    }
+
+    fn part2(map: Parsed) -> Result<Output> {
+        Ok(map
+            .trailheads(&map.analyse(Rules::default()))
+            .iter()
+            .map(|trailhead| trailhead.rating)
+            .sum())
+    }
+
+    fn list_trails(
+        map: &Map,
+        (x, y): (usize, usize),
+        rules: Rules,
+        limit: usize,
+    ) -> Result<(usize, Vec<String>)> {
+        let analysis = map.analyse(rules);
+        let trailhead = map
+            .trailheads(&analysis)
+            .into_iter()
+            .find(|trailhead| trailhead.position == (x, y))
+            .ok_or_else(|| anyhow!("There is no trailhead at {x},{y}"))?;
+        let trails = map
+            .trails(&analysis, (x, y), limit)
+            .into_iter()
+            .map(|trail| {
+                trail
+                    .into_iter()
+                    .map(|index| format!("{},{}", index % map.width, index / map.width))
+                    .collect::<Vec<_>>()
+                    .join(" -> ")
+            })
+            .collect();
+        Ok((trailhead.rating, trails))
     }
 }
 
 impl Parser {
     fn parse(input: &'static str) -> Result<Parsed> {
-        Ok(input
-            .lines()
-            .map(|line| {
-                line.bytes()
-                    .map(|b| if b == 46 { 100 } else { b - 48 })
-                    .collect()
-            })
-            .collect())
+        let width = input.lines().next().map_or(0, str::len);
+        if width == 0 {
+            bail!("The map is empty");
+        }
+        if input.lines().any(|line| line.len() != width) {
+            bail!("All rows of the map must have the same length");
+        }
+        Ok(Map {
+            width,
+            heights: input
+                .lines()
+                .flat_map(|line| {
+                    line.bytes().map(|b| match b {
+                        b'0'..=b'9' => Ok(b - b'0'),
+                        b'.' => Ok(IMPASSABLE),
+                        _ => Err(anyhow!("Invalid height '{}'", b as char)),
+                    })
+                })
+                .collect::<Result<_>>()?,
+        })
     }
 }
 
@@ -129,4 +274,103 @@ mod tests {
     test_example!("example1", Part1, 36);
 
     test_example!("example1", Part2, 81);
+
+    #[test]
+    fn trailheads() -> Result<()> {
+        let map = Parser::parse(include_str!("example1.txt"))?;
+        let trailheads = map.trailheads(&map.analyse(Rules::default()));
+        assert_eq!(
+            trailheads.iter().map(|t| t.score).collect::<Vec<_>>(),
+            [5, 6, 5, 3, 1, 3, 5, 3, 5]
+        );
+        assert_eq!(
+            trailheads.iter().map(|t| t.rating).collect::<Vec<_>>(),
+            [20, 24, 10, 4, 1, 4, 5, 8, 5]
+        );
+        assert_eq!(
+            trailheads[0],
+            Trailhead {
+                position: (2, 0),
+                score: 5,
+                rating: 20
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn rules() -> Result<()> {
+        let map = Parser::parse("0.2.4\n.1.3.\n024.9\n..6..\n56789")?;
+        let total = |map: &Map, rules| {
+            map.trailheads(&map.analyse(rules))
+                .iter()
+                .map(|t| (t.score, t.rating))
+                .fold((0, 0), |(s, r), (score, rating)| (s + score, r + rating))
+        };
+        assert_eq!(total(&map, Rules::default()), (0, 0));
+        assert_eq!(
+            total(
+                &map,
+                Rules {
+                    max_climb: 1,
+                    diagonal: true
+                }
+            ),
+            (0, 0)
+        );
+        assert_eq!(
+            total(
+                &map,
+                Rules {
+                    max_climb: 5,
+                    diagonal: false
+                }
+            ),
+            (1, 1)
+        );
+
+        let staircase = (0..10)
+            .map(|y| format!("{}{y}{}", ".".repeat(y), ".".repeat(9 - y)))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let map = Parser::parse(staircase.leak())?;
+        assert_eq!(total(&map, Rules::default()), (0, 0));
+        assert_eq!(
+            total(
+                &map,
+                Rules {
+                    max_climb: 1,
+                    diagonal: true
+                }
+            ),
+            (1, 1)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn list_trails() -> Result<()> {
+        let map = Parser::parse("0123\n1234\n8765\n9876")?;
+        let (rating, trails) = Day::list_trails(&map, (0, 0), Rules::default(), 2)?;
+        assert_eq!(rating, 16);
+        assert_eq!(
+            trails,
+            [
+                "0,0 -> 1,0 -> 2,0 -> 3,0 -> 3,1 -> 3,2 -> 3,3 -> 2,3 -> 1,3 -> 0,3",
+                "0,0 -> 1,0 -> 2,0 -> 3,0 -> 3,1 -> 3,2 -> 2,2 -> 2,3 -> 1,3 -> 0,3"
+            ]
+        );
+        let (_, all) = Day::list_trails(&map, (0, 0), Rules::default(), usize::MAX)?;
+        assert_eq!(all.len(), rating);
+        assert!(Day::list_trails(&map, (1, 0), Rules::default(), 1).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_maps() {
+        assert!(Parser::parse("").is_err());
+        assert!(Parser::parse("\n0123").is_err());
+        assert!(Parser::parse("0123\n12\n9876").is_err());
+        assert!(Parser::parse("0123\n1234\n98765").is_err());
+    }
 }
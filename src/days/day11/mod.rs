@@ -1,6 +1,6 @@
-use std::str::Bytes;
+use std::{collections::HashMap, str::Bytes};
 
-use memoize::memoize;
+use anyhow::anyhow;
 
 use crate::*;
 
@@ -9,32 +9,151 @@ day! {
     Parsed = StoneIter,
 }
 
+type Stone = u64;
+
+/// The stones that replace a stone when you blink.
+#[derive(Debug, PartialEq, Eq)]
+enum Replacement {
+    One(Stone),
+    Two(Stone, Stone),
+}
+
+/// A rule that changes a stone when you blink, if it applies to that stone.
+trait Rule {
+    fn apply(&self, stone: Stone) -> Option<Replacement>;
+}
+
+struct ZeroToOne;
+
+impl Rule for ZeroToOne {
+    fn apply(&self, stone: Stone) -> Option<Replacement> {
+        (stone == 0).then_some(Replacement::One(1))
+    }
+}
+
+struct SplitEvenDigits;
+
+impl Rule for SplitEvenDigits {
+    fn apply(&self, stone: Stone) -> Option<Replacement> {
+        let digits = digits(stone);
+        (digits & 1 == 0).then(|| {
+            let half = 10u64.pow(digits / 2);
+            Replacement::Two(stone / half, stone % half)
+        })
+    }
+}
+
+/// Multiplies the stone, unless the result doesn't fit.
+struct Multiply(Stone);
+
+impl Rule for Multiply {
+    fn apply(&self, stone: Stone) -> Option<Replacement> {
+        stone.checked_mul(self.0).map(Replacement::One)
+    }
+}
+
+const RULES: [&dyn Rule; 3] = [&ZeroToOne, &SplitEvenDigits, &Multiply(2024)];
+
+fn digits(stone: Stone) -> u32 {
+    stone.checked_ilog10().map_or(1, |log| log + 1)
+}
+
+/// Number of stones with the same engraving, which all change in the same way.
+type Counts = HashMap<Stone, usize>;
+
+fn add(counts: &mut Counts, stone: Stone, count: usize) -> Result<()> {
+    let total = counts.entry(stone).or_default();
+    *total = total
+        .checked_add(count)
+        .ok_or_else(|| anyhow!("Too many stones to count"))?;
+    Ok(())
+}
+
+fn total(counts: &Counts) -> Result<usize> {
+    counts
+        .values()
+        .try_fold(0usize, |total, &count| total.checked_add(count))
+        .ok_or_else(|| anyhow!("Too many stones to count"))
+}
+
+/// Changes all stones with the first rule that applies to them.
+fn blink(rules: &[&dyn Rule], counts: &Counts) -> Result<Counts> {
+    let mut next = Counts::with_capacity(counts.len() * 2);
+    for (&stone, &count) in counts {
+        let replacement = rules
+            .iter()
+            .find_map(|rule| rule.apply(stone))
+            .ok_or_else(|| anyhow!("No rule applies to stone {stone}"))?;
+        match replacement {
+            Replacement::One(stone) => add(&mut next, stone, count)?,
+            Replacement::Two(left, right) => {
+                add(&mut next, left, count)?;
+                add(&mut next, right, count)?;
+            }
+        }
+    }
+    Ok(next)
+}
+
+/// The counts of the stones after every blink, starting with the stones before blinking.
+fn blinks(
+    rules: &'static [&'static dyn Rule],
+    stones: impl Iterator<Item = Stone>,
+) -> impl Iterator<Item = Result<Counts>> {
+    let mut counts = Some(Ok(stones.fold(Counts::new(), |mut counts, stone| {
+        *counts.entry(stone).or_default() += 1;
+        counts
+    })));
+    std::iter::from_fn(move || {
+        let current = counts.take()?;
+        if let Ok(current) = &current {
+            counts = Some(blink(rules, current));
+        }
+        Some(current)
+    })
+}
+
 impl Day {
+    pub fn explain(part: Part) -> Result<String> {
+        Self::explanation(Parser::parse(include_str!("input.txt"))?, part)
+    }
+
     fn part1(stones: Parsed) -> Result<Output> {
-        Ok(stones.map(|stone| count_after_blinks(stone, 25)).sum())
+        Self::count_after_blinks(&RULES, stones, 25)
     }
 
     fn part2(stones: Parsed) -> Result<Output> {
-        Ok(stones.map(|stone| count_after_blinks(stone, 75)).sum())
+        Self::count_after_blinks(&RULES, stones, 75)
     }
-}
 
-#[memoize]
+    fn count_after_blinks(
+        rules: &'static [&'static dyn Rule],
+        stones: Parsed,
+        times: usize,
+    ) -> Result<Output> {
+        let mut counts = Counts::new();
+        for blink in blinks(rules, stones).take(times.saturating_add(1)) {
+            counts = blink?;
+        }
+        total(&counts)
+    }
 
-fn count_after_blinks(stone: u64, blinks: u8) -> usize {
-    if blinks == 0 {
-        1
-    } else if stone == 0 {
-        count_after_blinks(1, blinks - 1)
-    } else {
-        let digits = ((stone + 1) as f64).log10().ceil() as u32;
-        if digits % 2 == 0 {
-            let half_div = 10u64.pow(digits / 2);
-            count_after_blinks(stone / half_div, blinks - 1)
-                + count_after_blinks(stone % half_div, blinks - 1)
-        } else {
-            count_after_blinks(stone * 2024, blinks - 1)
+    /// Number of stones and distinct engravings after every blink.
+    fn explanation(stones: Parsed, part: Part) -> Result<String> {
+        let times = match part {
+            Part1 => 25,
+            Part2 => 75,
+        };
+        let mut explanation = String::new();
+        for (blink, counts) in blinks(&RULES, stones).take(times + 1).enumerate() {
+            let counts = counts?;
+            explanation += &format!(
+                "Blink {blink}: {} stones, {} distinct\n",
+                total(&counts)?,
+                counts.len()
+            );
         }
+        Ok(explanation)
     }
 }
 
@@ -89,4 +208,95 @@ mod tests {
     run!(Part2);
 
     test_example!("example1", Part1, 55312);
+
+    #[test]
+    fn count_digits() {
+        for (stone, expected) in [
+            (0, 1),
+            (9, 1),
+            (10, 2),
+            (99, 2),
+            (100, 3),
+            (999_999_999_999_999, 15),
+            (1_000_000_000_000_000, 16),
+            (u64::MAX, 20),
+        ] {
+            assert_eq!(digits(stone), expected);
+        }
+    }
+
+    #[test]
+    fn rules() {
+        assert_eq!(SplitEvenDigits.apply(1000), Some(Replacement::Two(10, 0)));
+        assert_eq!(SplitEvenDigits.apply(99_999), None);
+        assert_eq!(ZeroToOne.apply(0), Some(Replacement::One(1)));
+        assert_eq!(Multiply(2024).apply(u64::MAX / 1000), None);
+    }
+
+    #[test]
+    fn example_blinks() -> Result<()> {
+        let counts = blinks(&RULES, StoneIter::from("125 17"))
+            .take(7)
+            .map(|counts| counts.map(|counts| counts.values().sum()))
+            .collect::<Result<Vec<usize>>>()?;
+        assert_eq!(counts, [2, 3, 4, 5, 9, 13, 22]);
+        Ok(())
+    }
+
+    #[test]
+    fn too_many_stones() -> Result<()> {
+        /// Every stone turns into two of the same stones, doubling the count every blink.
+        struct Duplicate;
+
+        impl Rule for Duplicate {
+            fn apply(&self, stone: Stone) -> Option<Replacement> {
+                Some(Replacement::Two(stone, stone))
+            }
+        }
+
+        const DUPLICATE: [&dyn Rule; 1] = [&Duplicate];
+        assert_eq!(
+            Day::count_after_blinks(&DUPLICATE, StoneIter::from("7"), 63)?,
+            1 << 63
+        );
+        assert!(Day::count_after_blinks(&DUPLICATE, StoneIter::from("7"), 64).is_err());
+        assert!(Day::count_after_blinks(&DUPLICATE, StoneIter::from("7 8"), 63).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn variant_rules() -> Result<()> {
+        const TRIPLE: [&dyn Rule; 3] = [&ZeroToOne, &SplitEvenDigits, &Multiply(3)];
+        // 1, 3, 9, 27, 2 7, 6 21, 18 2 1, 1 8 6 3
+        let counts = blinks(&TRIPLE, StoneIter::from("1")).nth(7).unwrap()?;
+        assert_eq!(counts.values().sum::<usize>(), 4);
+        assert_eq!(counts.len(), 4);
+
+        const ONLY_SPLIT: [&dyn Rule; 1] = [&SplitEvenDigits];
+        let mut blinks = blinks(&ONLY_SPLIT, StoneIter::from("1234"));
+        assert_eq!(
+            blinks.nth(2).unwrap()?,
+            Counts::from([(1, 1), (2, 1), (3, 1), (4, 1)])
+        );
+        assert!(blinks.next().unwrap().is_err());
+        assert!(blinks.next().is_none());
+
+        assert_eq!(
+            Day::count_after_blinks(&ONLY_SPLIT, StoneIter::from("1234"), 2)?,
+            4
+        );
+        assert!(Day::count_after_blinks(&ONLY_SPLIT, StoneIter::from("1234"), 5).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn explanation() -> Result<()> {
+        let explanation = Day::explanation(StoneIter::from("0"), Part2)?;
+        let lines = explanation.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 76);
+        assert_eq!(lines[0], "Blink 0: 1 stones, 1 distinct");
+        assert_eq!(lines[4], "Blink 4: 4 stones, 3 distinct");
+        assert!(lines[75].ends_with(" 54 distinct"));
+        Ok(())
+    }
 }
@@ -1,8 +1,7 @@
-use std::collections::BTreeSet;
+use anyhow::bail;
 
 use crate::*;
-
-use Direction::*;
+use visualize::{Canvas, Cell, Frame, Rgb};
 
 day! {
     Output = usize,
@@ -10,261 +9,170 @@ day! {
     bench_sample_size: 50,
 }
 
+const DIRECTIONS: [(isize, isize); 4] = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+
 struct Map {
-    plots: &'static [u8],
+    plots: Vec<u8>,
     width: usize,
-    width_incl_newline: usize,
-    heigth: usize,
+    height: usize,
 }
 
-type FencesPart1 = usize;
-type FencesPart2 = BTreeSet<FenceLocation>;
-
-#[derive(Default)]
-struct Region<F: Fences> {
+#[derive(Debug, PartialEq, Eq)]
+struct Region {
+    plant: u8,
     area: usize,
-    fences: F,
-}
-
-#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
-enum Direction {
-    Right,
-    Left,
-    Up,
-    Down,
-}
-
-#[derive(Default, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
-struct Location {
-    x: isize,
-    y: isize,
-}
-
-#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
-struct FenceLocation {
-    direction: Direction,
-    a: isize,
-    b: isize,
-}
-
-struct LocationSet<'a> {
-    map: &'a Map,
-    locations: [bool; 20_000],
-}
-
-impl Location {
-    fn next(&self, direction: Direction) -> Location {
-        match direction {
-            Right => Self {
-                x: self.x + 1,
-                y: self.y,
-            },
-            Left => Self {
-                x: self.x - 1,
-                y: self.y,
-            },
-            Up => Self {
-                x: self.x,
-                y: self.y - 1,
-            },
-            Down => Self {
-                x: self.x,
-                y: self.y + 1,
-            },
-        }
-    }
-}
-
-impl<'a> LocationSet<'a> {
-    fn with_map(map: &'a Map) -> Self {
-        LocationSet {
-            map,
-            locations: [false; 20_000],
-        }
-    }
-
-    fn insert(&mut self, location: Location) {
-        self.locations[location.y as usize * self.map.width_incl_newline + location.x as usize] =
-            true;
-    }
-
-    fn contains(&mut self, location: Location) -> bool {
-        self.locations[location.y as usize * self.map.width_incl_newline + location.x as usize]
-    }
-
-    fn remove(&mut self, location: Location) {
-        self.locations[location.y as usize * self.map.width_incl_newline + location.x as usize] =
-            false;
-    }
-
-    fn first(&mut self) -> Option<Location> {
-        self.locations
-            .iter()
-            .enumerate()
-            .find(|(_, contains)| **contains)
-            .map(|(idx, _)| Location {
-                x: (idx % self.map.width_incl_newline) as isize,
-                y: (idx / self.map.width_incl_newline) as isize,
-            })
-    }
-}
-
-trait Fences: Default {
-    fn insert(&mut self, location: Location, direction: Direction);
-    fn len(&self) -> usize;
-    fn iter(&self) -> impl Iterator<Item = &FenceLocation>;
-}
-
-impl Fences for FencesPart1 {
-    fn insert(&mut self, _location: Location, _direction: Direction) {
-        *self += 1;
-    }
-
-    fn len(&self) -> usize {
-        *self
-    }
-
-    fn iter(&self) -> impl Iterator<Item = &FenceLocation> {
-        std::vec::IntoIter::<_>::default()
-    }
+    perimeter: usize,
+    sides: usize,
 }
 
-impl Fences for FencesPart2 {
-    fn insert(&mut self, location: Location, direction: Direction) {
-        match direction {
-            Left | Right => self.insert(FenceLocation {
-                direction,
-                a: location.x,
-                b: location.y,
-            }),
-            Up | Down => self.insert(FenceLocation {
-                direction,
-                a: location.y,
-                b: location.x,
-            }),
-        };
-    }
-
-    fn len(&self) -> usize {
-        self.len()
-    }
-
-    fn iter(&self) -> impl Iterator<Item = &FenceLocation> {
-        self.iter()
-    }
+/// The regions of the map, and for every plot the index of the region it belongs to.
+struct Regions {
+    regions: Vec<Region>,
+    plots: Vec<usize>,
 }
 
-impl<F: Fences> Region<F> {
-    fn total_price(&self) -> Output {
-        self.area * self.fences.len()
+impl Region {
+    fn price(&self) -> Output {
+        self.area * self.perimeter
     }
 
     fn bulk_discount(&self) -> Output {
-        self.area * self.count_sides()
-    }
-
-    fn count_sides(&self) -> Output {
-        let mut fences_iter = self.fences.iter();
-        let mut prev = fences_iter.next().unwrap();
-        let mut sides = 1;
-        for next in fences_iter {
-            if next.direction != prev.direction || next.a != prev.a || next.b != prev.b + 1 {
-                sides += 1;
-            }
-            prev = next;
-        }
-        sides
+        self.area * self.sides
     }
 }
 
 impl Map {
-    fn get(&self, location: Location) -> Option<u8> {
-        if location.y < 0
-            || location.y as usize >= self.heigth
-            || location.x < 0
-            || location.x as usize >= self.width
-        {
-            None
-        } else {
-            Some(self.plots[location.y as usize * self.width_incl_newline + location.x as usize])
-        }
-    }
-
-    fn regions<F: Fences>(&self) -> Vec<Region<F>> {
-        let mut visited = LocationSet::with_map(self);
-        let mut visit = LocationSet::with_map(self);
-        let mut regions = vec![];
+    fn get(&self, x: isize, y: isize) -> Option<u8> {
+        (x >= 0 && y >= 0 && (x as usize) < self.width && (y as usize) < self.height)
+            .then(|| self.plots[y as usize * self.width + x as usize])
+    }
+
+    /// Finds the regions with a flood fill, measuring them along the way. A region has as many
+    /// sides as corners, so the sides are counted by checking the four corners of every plot.
+    fn regions(&self) -> Regions {
+        let mut plots = vec![usize::MAX; self.plots.len()];
+        let mut regions = Vec::new();
+        let mut stack = Vec::new();
+        for start in 0..self.plots.len() {
+            if plots[start] != usize::MAX {
+                continue;
+            }
+            let plant = self.plots[start];
+            let mut region = Region {
+                plant,
+                area: 0,
+                perimeter: 0,
+                sides: 0,
+            };
+            plots[start] = regions.len();
+            stack.push(start);
+            while let Some(index) = stack.pop() {
+                let (x, y) = ((index % self.width) as isize, (index / self.width) as isize);
+                let same = |dx, dy| self.get(x + dx, y + dy) == Some(plant);
+                region.area += 1;
+                for (i, (dx, dy)) in DIRECTIONS.into_iter().enumerate() {
+                    if !same(dx, dy) {
+                        region.perimeter += 1;
+                    } else {
+                        let next = (y + dy) as usize * self.width + (x + dx) as usize;
+                        if plots[next] == usize::MAX {
+                            plots[next] = regions.len();
+                            stack.push(next);
+                        }
+                    }
 
-        visit.insert(Location::default());
-        while let Some(location) = visit.first() {
-            let plant = self.get(location).unwrap();
-            let mut region = Region::default();
-            self.find_regions(location, plant, &mut region, &mut visited, &mut visit);
+                    // The corner between this direction and the next one clockwise
+                    let (cx, cy) = DIRECTIONS[(i + 1) % 4];
+                    let (a, b) = (same(dx, dy), same(cx, cy));
+                    if (!a && !b) || (a && b && !same(dx + cx, dy + cy)) {
+                        region.sides += 1;
+                    }
+                }
+            }
             regions.push(region);
         }
-
-        regions
+        Regions { regions, plots }
     }
 
-    fn find_regions<F: Fences>(
-        &self,
-        location: Location,
-        plant: u8,
-        region: &mut Region<F>,
-        visited: &mut LocationSet,
-        visit: &mut LocationSet,
-    ) {
-        visit.remove(location);
-        visited.insert(location);
-        region.area += 1;
-
-        for direction in [Right, Down, Left, Up] {
-            let next_location = location.next(direction);
-            if let Some(next_plant) = self.get(next_location) {
-                if next_plant == plant {
-                    if !visited.contains(next_location) {
-                        self.find_regions(next_location, plant, region, visited, visit);
-                    }
-                } else {
-                    region.fences.insert(location, direction);
-                    if !visited.contains(next_location) {
-                        visit.insert(next_location);
-                    }
-                }
-            } else {
-                region.fences.insert(location, direction);
-            }
+    fn canvas(&self, regions: &Regions) -> Canvas {
+        let mut canvas = Canvas::new(self.width, self.height, Cell::default());
+        for (index, &region) in regions.plots.iter().enumerate() {
+            canvas.set(
+                index % self.width,
+                index / self.width,
+                Cell::new(self.plots[index] as char, Self::color(region)),
+            );
+        }
+        canvas
+    }
+
+    /// A colour for every region, stepping around the colour wheel by the golden angle so
+    /// regions found after each other get clearly different colours.
+    fn color(region: usize) -> Rgb {
+        let hue = (region as f32 * 0.618_034).fract() * 6.0;
+        let rising = (hue.fract() * 200.0) as u8 + 55;
+        let falling = 255 - rising + 55;
+        match hue as usize {
+            0 => Rgb(255, rising, 55),
+            1 => Rgb(falling, 255, 55),
+            2 => Rgb(55, 255, rising),
+            3 => Rgb(55, falling, 255),
+            4 => Rgb(rising, 55, 255),
+            _ => Rgb(255, 55, falling),
         }
     }
 }
 
 impl Day {
+    pub fn explain(part: Part) -> Result<String> {
+        Ok(Self::explanation(
+            &Parser::parse(include_str!("input.txt"))?,
+            part,
+        ))
+    }
+
     fn part1(map: Parsed) -> Result<Output> {
-        Ok(map
-            .regions()
-            .into_iter()
-            .map(|region: Region<FencesPart1>| region.total_price())
-            .sum())
+        let regions = map.regions();
+        visualize::record(|| map.canvas(&regions));
+        Ok(regions.regions.iter().map(Region::price).sum())
     }
 
     fn part2(map: Parsed) -> Result<Output> {
-        Ok(map
-            .regions()
-            .into_iter()
-            .map(|region: Region<FencesPart2>| region.bulk_discount())
-            .sum())
+        let regions = map.regions();
+        visualize::record(|| map.canvas(&regions));
+        Ok(regions.regions.iter().map(Region::bulk_discount).sum())
+    }
+
+    fn explanation(map: &Map, part: Part) -> String {
+        let regions = map.regions();
+        let mut explanation = map.canvas(&regions).to_ansi();
+        let mut total = 0;
+        for region in &regions.regions {
+            let price = match part {
+                Part1 => region.price(),
+                Part2 => region.bulk_discount(),
+            };
+            total += price;
+            explanation += &format!(
+                "{}: area {}, perimeter {}, sides {}, price {price}\n",
+                region.plant as char, region.area, region.perimeter, region.sides
+            );
+        }
+        explanation + &format!("Total price: {total}\n")
     }
 }
 
 impl Parser {
     fn parse(input: &'static str) -> Result<Parsed> {
-        let width = input.find('\n').unwrap();
-        let width_incl_newline = width + 1;
+        let width = input.lines().next().map_or(0, str::len);
+        if input.lines().any(|line| line.len() != width) {
+            bail!("All rows of the map must have the same length");
+        }
+        let plots = input.lines().flat_map(str::bytes).collect::<Vec<_>>();
         Ok(Map {
-            plots: input.as_bytes(),
+            height: plots.len() / width.max(1),
+            plots,
             width,
-            width_incl_newline,
-            heigth: (input.len() + 1) / width_incl_newline,
         })
     }
 }
@@ -292,4 +200,56 @@ mod tests {
     test_example!("example4", Part2, 236);
 
     test_example!("example5", Part2, 368);
+
+    #[test]
+    fn regions() -> Result<()> {
+        let map = Parser::parse(include_str!("example2.txt"))?;
+        let regions = map.regions();
+        assert_eq!(regions.regions.len(), 5);
+        assert_eq!(
+            regions.regions[0],
+            Region {
+                plant: b'O',
+                area: 21,
+                perimeter: 36,
+                sides: 20
+            }
+        );
+        assert_eq!(regions.plots[6], 1);
+        assert_eq!(
+            map.canvas(&regions).to_ascii().trim_end(),
+            include_str!("example2.txt")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn large_map() -> Result<()> {
+        let row = "AB".repeat(150) + "\n";
+        let map = Parser::parse(row.repeat(300).leak())?;
+        let regions = map.regions();
+        assert_eq!(regions.regions.len(), 300);
+        assert!(regions
+            .regions
+            .iter()
+            .all(|region| region.area == 300 && region.sides == 4));
+        Ok(())
+    }
+
+    #[test]
+    fn jagged_map() {
+        assert!(Parser::parse("AAB\nAB\nBBB").is_err());
+        assert!(Parser::parse("\nAA").is_err());
+    }
+
+    #[test]
+    fn explanation() -> Result<()> {
+        let map = Parser::parse(include_str!("example1.txt"))?;
+        let explanation = Day::explanation(&map, Part2);
+        let lines = explanation.lines().collect::<Vec<_>>();
+        assert_eq!(lines[4], "A: area 4, perimeter 10, sides 4, price 16");
+        assert_eq!(lines[8], "E: area 3, perimeter 8, sides 4, price 12");
+        assert_eq!(lines[9], "Total price: 80");
+        Ok(())
+    }
 }
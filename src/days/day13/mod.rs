@@ -7,6 +7,9 @@ use nom::{
     sequence::{pair, preceded, separated_pair, tuple},
 };
 
+use anyhow::anyhow;
+use num_integer::{ExtendedGcd, Integer};
+
 use crate::*;
 
 day! {
@@ -14,61 +17,179 @@ day! {
     Parsed = Vec<Machine>,
 }
 
+type Number = i128;
+
 struct Machine {
     button_a: Position,
     button_b: Position,
     prize: Position,
 }
 
+#[derive(Clone, Copy)]
 struct Position {
-    x: Output,
-    y: Output,
+    x: Number,
+    y: Number,
+}
+
+/// Distance added to every prize and the tokens it costs to press each button.
+struct Config {
+    offset: Number,
+    cost_a: Number,
+    cost_b: Number,
+}
+
+impl Config {
+    const PART1: Config = Config {
+        offset: 0,
+        cost_a: 3,
+        cost_b: 1,
+    };
+
+    const PART2: Config = Config {
+        offset: 10_000_000_000_000,
+        cost_a: 3,
+        cost_b: 1,
+    };
 }
 
 impl Machine {
-    fn fewest_tokens(self) -> Output {
-        // Button A: X+94, Y+34
-        // Button B: X+22, Y+67
-        // Prize: X=8400, Y=5400
-        // A = (67 * 8400 - 22 * 5400) / (67 * 94 – 22 * 34)
-
-        let a_teller = self.button_b.y * self.prize.x - self.button_b.x * self.prize.y;
-        let a_noemer = self.button_b.y * self.button_a.x - self.button_b.x * self.button_a.y;
-        if a_teller % a_noemer == 0 {
-            let a = a_teller / a_noemer;
-            let b_teller = self.prize.x - a * self.button_a.x;
-            let b_noemer = self.button_b.x;
-            if b_teller % b_noemer == 0 {
-                let b = b_teller / b_noemer;
-                return 3 * a + b;
+    /// The fewest tokens to win the prize, or `None` when the prize can't be won. Costs are
+    /// expected to be non-negative and buttons only move the claw forward.
+    fn fewest_tokens(&self, config: &Config) -> Result<Option<Number>> {
+        let prize = Position {
+            x: checked(self.prize.x.checked_add(config.offset))?,
+            y: checked(self.prize.y.checked_add(config.offset))?,
+        };
+        let (a, b) = (self.button_a, self.button_b);
+
+        let Some((presses_a, presses_b)) = (match cross(a, b)? {
+            0 => Self::solve_collinear(a, b, prize, config)?,
+            determinant => {
+                // Cramer's rule
+                let presses_a = cross(prize, b)?;
+                let presses_b = cross(a, prize)?;
+                (presses_a % determinant == 0 && presses_b % determinant == 0)
+                    .then(|| (presses_a / determinant, presses_b / determinant))
             }
+        }) else {
+            return Ok(None);
+        };
+
+        if presses_a < 0 || presses_b < 0 {
+            return Ok(None);
         }
-        0
+        Ok(Some(checked(
+            presses_a
+                .checked_mul(config.cost_a)
+                .zip(presses_b.checked_mul(config.cost_b))
+                .and_then(|(a, b)| a.checked_add(b)),
+        )?))
     }
 
-    fn into_part2(self) -> Self {
-        Machine {
-            button_a: self.button_a,
-            button_b: self.button_b,
-            prize: Position {
-                x: self.prize.x + 10_000_000_000_000,
-                y: self.prize.y + 10_000_000_000_000,
-            },
+    /// When both buttons move the claw along the same line, the prize has to be on that line
+    /// too, and only the distance along the line matters.
+    fn solve_collinear(
+        a: Position,
+        b: Position,
+        prize: Position,
+        config: &Config,
+    ) -> Result<Option<(Number, Number)>> {
+        if cross(a, prize)? != 0 || cross(b, prize)? != 0 {
+            return Ok(None);
         }
+        let solution = if a.x != 0 || b.x != 0 {
+            solve_line(a.x, b.x, prize.x, config)?
+        } else {
+            solve_line(a.y, b.y, prize.y, config)?
+        };
+        let Some((presses_a, presses_b)) = solution else {
+            return Ok(None);
+        };
+        let reaches = |a: Number, b: Number, target: Number| -> Result<bool> {
+            Ok(checked(
+                presses_a
+                    .checked_mul(a)
+                    .zip(presses_b.checked_mul(b))
+                    .and_then(|(a, b)| a.checked_add(b)),
+            )? == target)
+        };
+        Ok((reaches(a.x, b.x, prize.x)? && reaches(a.y, b.y, prize.y)?)
+            .then_some((presses_a, presses_b)))
     }
 }
 
+/// The cheapest non-negative solution of `presses_a * a + presses_b * b = target`.
+fn solve_line(
+    a: Number,
+    b: Number,
+    target: Number,
+    config: &Config,
+) -> Result<Option<(Number, Number)>> {
+    Ok(match (a, b) {
+        (0, 0) => (target == 0).then_some((0, 0)),
+        (0, b) => (target % b == 0 && target / b >= 0).then(|| (0, target / b)),
+        (a, 0) => (target % a == 0 && target / a >= 0).then(|| (target / a, 0)),
+        (a, b) => {
+            let ExtendedGcd { gcd, x, y } = a.extended_gcd(&b);
+            if target % gcd != 0 {
+                return Ok(None);
+            }
+            // All solutions are (presses_a + k * step_a, presses_b - k * step_b)
+            let presses_a = checked(x.checked_mul(target / gcd))?;
+            let presses_b = checked(y.checked_mul(target / gcd))?;
+            let (step_a, step_b) = (b / gcd, a / gcd);
+            let min_k = Integer::div_ceil(&checked(presses_a.checked_neg())?, &step_a);
+            let max_k = Integer::div_floor(&presses_b, &step_b);
+            if min_k > max_k {
+                return Ok(None);
+            }
+
+            let slope = checked(
+                config
+                    .cost_a
+                    .checked_mul(step_a)
+                    .zip(config.cost_b.checked_mul(step_b))
+                    .and_then(|(a, b)| a.checked_sub(b)),
+            )?;
+            let k = if slope >= 0 { min_k } else { max_k };
+            Some((
+                checked(k.checked_mul(step_a).and_then(|n| presses_a.checked_add(n)))?,
+                checked(k.checked_mul(step_b).and_then(|n| presses_b.checked_sub(n)))?,
+            ))
+        }
+    })
+}
+
+fn cross(lhs: Position, rhs: Position) -> Result<Number> {
+    checked(
+        lhs.x
+            .checked_mul(rhs.y)
+            .zip(rhs.x.checked_mul(lhs.y))
+            .and_then(|(a, b)| a.checked_sub(b)),
+    )
+}
+
+fn checked(number: Option<Number>) -> Result<Number> {
+    number.ok_or_else(|| anyhow!("Arithmetic overflow while solving a claw machine"))
+}
+
 impl Day {
     fn part1(machines: Parsed) -> Result<Output> {
-        Ok(machines.into_iter().map(Machine::fewest_tokens).sum())
+        Self::total_tokens(&machines, &Config::PART1)
     }
 
     fn part2(machines: Parsed) -> Result<Output> {
-        Ok(machines
-            .into_iter()
-            .map(Machine::into_part2)
-            .map(Machine::fewest_tokens)
-            .sum())
+        Self::total_tokens(&machines, &Config::PART2)
+    }
+
+    fn total_tokens(machines: &[Machine], config: &Config) -> Result<Output> {
+        let mut total: Number = 0;
+        for machine in machines {
+            if let Some(tokens) = machine.fewest_tokens(config)? {
+                total = checked(total.checked_add(tokens))?;
+            }
+        }
+        Ok(Output::try_from(total)?)
     }
 }
 
@@ -100,8 +221,8 @@ impl Parser {
                 u64,
             ),
             |(x, y)| Position {
-                x: x as Output,
-                y: y as Output,
+                x: x as Number,
+                y: y as Number,
             },
         )(s)
     }
@@ -110,8 +231,8 @@ impl Parser {
         map(
             separated_pair(preceded(tag("Prize: X="), u64), tag(", Y="), u64),
             |(x, y)| Position {
-                x: x as Output,
-                y: y as Output,
+                x: x as Number,
+                y: y as Number,
             },
         )(s)
     }
@@ -128,4 +249,83 @@ mod tests {
     test_example!("example1", Part1, 480);
 
     test_example!("example1", Part2, 875_318_608_908_isize);
+
+    fn machine(
+        button_a: (Number, Number),
+        button_b: (Number, Number),
+        prize: (Number, Number),
+    ) -> Machine {
+        let position = |(x, y)| Position { x, y };
+        Machine {
+            button_a: position(button_a),
+            button_b: position(button_b),
+            prize: position(prize),
+        }
+    }
+
+    #[test]
+    fn collinear_buttons() -> Result<()> {
+        let cheap_b = Config::PART1;
+        let cheap_a = Config {
+            offset: 0,
+            cost_a: 1,
+            cost_b: 3,
+        };
+        let claw = machine((2, 4), (3, 6), (10, 20));
+        assert_eq!(claw.fewest_tokens(&cheap_b)?, Some(8));
+        assert_eq!(claw.fewest_tokens(&cheap_a)?, Some(5));
+        let claw = machine((0, 2), (0, 3), (0, 7));
+        assert_eq!(claw.fewest_tokens(&cheap_b)?, Some(7));
+        let claw = machine((0, 0), (0, 0), (0, 0));
+        assert_eq!(claw.fewest_tokens(&cheap_b)?, Some(0));
+        let claw = machine((0, 0), (2, 2), (6, 6));
+        assert_eq!(claw.fewest_tokens(&cheap_b)?, Some(3));
+        let claw = machine((3, 3), (5, 5), (1, 1));
+        assert_eq!(claw.fewest_tokens(&cheap_b)?, None);
+
+        for (button_a, button_b, prize) in [
+            ((2, 4), (3, 6), (10, 21)),
+            ((2, 4), (4, 8), (3, 6)),
+            ((0, 0), (0, 0), (1, 0)),
+            ((0, 0), (2, 2), (6, 5)),
+        ] {
+            assert_eq!(
+                machine(button_a, button_b, prize).fewest_tokens(&cheap_b)?,
+                None
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn configurable() -> Result<()> {
+        let claw = machine((94, 34), (22, 67), (0, 0));
+        let config = Config {
+            offset: 5400,
+            cost_a: 2,
+            cost_b: 5,
+        };
+        assert_eq!(claw.fewest_tokens(&config)?, None);
+        let claw = machine((94, 34), (22, 67), (3000, 0));
+        assert_eq!(claw.fewest_tokens(&config)?, Some(2 * 80 + 5 * 40));
+        Ok(())
+    }
+
+    #[test]
+    fn negative_presses() -> Result<()> {
+        let claw = machine((1, 1), (1, 2), (3, 2));
+        assert_eq!(claw.fewest_tokens(&Config::PART1)?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn overflow() {
+        let huge = Number::MAX / 2;
+        let claw = machine((huge, 1), (1, huge), (huge, huge));
+        assert!(claw.fewest_tokens(&Config::PART1).is_err());
+        assert!(
+            Day::total_tokens(&[machine((1, 0), (0, 1), (1 << 70, 0))], &Config::PART1).is_err()
+        );
+        assert!(solve_line(1, Number::MAX, Number::MIN, &Config::PART1).is_err());
+    }
 }
@@ -1,4 +1,6 @@
-use std::cmp::Ordering;
+mod tree;
+
+use std::{cmp::Ordering, path::Path};
 
 use nom::{
     bytes::complete::tag,
@@ -8,7 +10,9 @@ use nom::{
     sequence::{preceded, separated_pair},
 };
 
+use crate::visualize::{Canvas, Cell, Frame, Rgb};
 use crate::*;
+use tree::{find_tree, largest_group};
 
 day! {
     Output = i32,
@@ -49,26 +53,59 @@ impl From<Vec<Robot>> for Robots {
 }
 
 impl Day {
+    pub fn tree(output: Option<&Path>, scale: usize) -> Result<String> {
+        let robots = Parser::parse(include_str!("input.txt"))?;
+        let seconds = find_tree(&robots)?;
+        let frame = Self::frame(&robots, seconds);
+        if let Some(path) = output {
+            visualize::save(&frame, path, scale)?;
+        }
+
+        Ok(format!(
+            "After {seconds} seconds, with a largest group of {} robots:\n{}",
+            largest_group(&robots, seconds),
+            frame.to_ascii()
+        ))
+    }
+
     fn part1(robots: Parsed) -> Result<Output> {
+        for i in 0..=100 {
+            visualize::record(|| Self::frame(&robots, i));
+        }
         Ok(Self::safety_factor(&robots, 100))
     }
 
     fn part2(robots: Parsed) -> Result<Output> {
-        Ok((1..10_000)
-            .map(|i| (i, Self::safety_factor(&robots, i)))
-            .min_by_key(|(_, safety_factor)| *safety_factor)
-            .unwrap()
-            .0)
+        let seconds = find_tree(&robots)?;
+        visualize::record(|| Self::frame(&robots, seconds));
+        Ok(seconds)
+    }
+
+    fn positions(robots: &Parsed, i: Output) -> impl Iterator<Item = Position> + use<'_> {
+        robots.robots.iter().map(move |robot| Position {
+            x: (robot.position.x + i * robot.velocity.x).rem_euclid(robots.width),
+            y: (robot.position.y + i * robot.velocity.y).rem_euclid(robots.height),
+        })
+    }
+
+    fn frame(robots: &Parsed, i: Output) -> Canvas {
+        let mut canvas = Canvas::new(
+            robots.width as usize,
+            robots.height as usize,
+            Cell::new('.', Rgb::BLACK),
+        );
+        for position in Self::positions(robots, i) {
+            canvas.set(
+                position.x as usize,
+                position.y as usize,
+                Cell::new('#', Rgb::GREEN),
+            );
+        }
+        canvas
     }
 
     fn safety_factor(robots: &Parsed, i: Output) -> Output {
-        robots
-            .robots
-            .iter()
-            .map(|robot| Position {
-                x: (robot.position.x + i * robot.velocity.x).rem_euclid(robots.width),
-                y: (robot.position.y + i * robot.velocity.y).rem_euclid(robots.height),
-            })
+        Self::positions(robots, i)
             .map(|position| {
                 (
                     position.x.cmp(&(robots.width / 2)),
@@ -127,4 +164,73 @@ mod tests {
     run!(Part2);
 
     test_example!("example1", Part1, 12);
+
+    /// Robots that form a filled triangle after `seconds`, plus robots at random positions.
+    fn planted_tree(seconds: Output) -> Robots {
+        let mut random = Random::new(2024);
+        let mut random = |max: Output| random.below(max as u64) as Output;
+
+        let (width, height) = (101, 103);
+        let mut robots = Vec::new();
+        for row in 0..15 {
+            for column in -row..=row {
+                robots.push((50 + column, 40 + row));
+            }
+        }
+        for _ in 0..250 {
+            let position = (random(width), random(height));
+            robots.push(position);
+        }
+
+        Robots {
+            robots: robots
+                .into_iter()
+                .map(|(x, y)| {
+                    let velocity = Position {
+                        x: random(2 * width - 1) - width + 1,
+                        y: random(2 * height - 1) - height + 1,
+                    };
+                    Robot {
+                        position: Position {
+                            x: (x - seconds * velocity.x).rem_euclid(width),
+                            y: (y - seconds * velocity.y).rem_euclid(height),
+                        },
+                        velocity,
+                    }
+                })
+                .collect(),
+            width,
+            height,
+        }
+    }
+
+    #[test]
+    fn find_planted_tree() -> Result<()> {
+        for seconds in [0, 1, 6_000, 10_402] {
+            let robots = planted_tree(seconds);
+            assert_eq!(find_tree(&robots)?, seconds);
+            assert!(largest_group(&robots, seconds) >= 225);
+            assert!(largest_group(&robots, seconds + 1) < 50);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn dimensions_not_coprime() {
+        let robot = |x, y, vx, vy| Robot {
+            position: Position { x, y },
+            velocity: Position { x: vx, y: vy },
+        };
+        let robots = Robots {
+            robots: vec![
+                robot(0, 1, 1, 0),
+                robot(0, 1, 0, 0),
+                robot(0, 0, 0, 1),
+                robot(0, 1, 0, 0),
+            ],
+            width: 2,
+            height: 2,
+        };
+        assert!(find_tree(&robots).is_err_and(|err| err.to_string().contains("coprime")));
+    }
 }
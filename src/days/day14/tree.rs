@@ -0,0 +1,71 @@
+use std::collections::HashSet;
+
+use anyhow::anyhow;
+
+use super::{Day, Output, Robots};
+use crate::Result;
+
+/// Finds the second within the full period of `width * height` seconds at which the robots
+/// are clustered the most. Horizontal positions repeat every `width` seconds and vertical
+/// positions every `height` seconds, so the spread of each axis is minimized within its own
+/// period and both seconds are combined with the Chinese remainder theorem.
+pub fn find_tree(robots: &Robots) -> Result<Output> {
+    let x = least_spread(robots.width, |i| {
+        Day::positions(robots, i).map(|position| position.x)
+    });
+    let y = least_spread(robots.height, |i| {
+        Day::positions(robots, i).map(|position| position.y)
+    });
+
+    (0..robots.height)
+        .map(|k| x + k * robots.width)
+        .find(|seconds| seconds % robots.height == y)
+        .ok_or_else(|| {
+            anyhow!(
+                "Can not combine the clustered seconds of both axes, the width {} and height {} \
+                 must be coprime",
+                robots.width,
+                robots.height
+            )
+        })
+}
+
+/// The second within `period` with the lowest variance of the coordinates.
+fn least_spread<I: Iterator<Item = Output>>(
+    period: Output,
+    coordinates: impl Fn(Output) -> I,
+) -> Output {
+    (0..period)
+        .min_by_key(|&i| {
+            let (n, sum, squares) = coordinates(i)
+                .fold((0i64, 0i64, 0i64), |(n, sum, squares), c| {
+                    (n + 1, sum + c as i64, squares + c as i64 * c as i64)
+                });
+            n * squares - sum * sum
+        })
+        .unwrap_or(0)
+}
+
+/// Size of the largest group of robots that are connected horizontally or vertically.
+pub fn largest_group(robots: &Robots, seconds: Output) -> usize {
+    let mut remaining = Day::positions(robots, seconds)
+        .map(|position| (position.x, position.y))
+        .collect::<HashSet<_>>();
+
+    let mut largest = 0;
+    while let Some(&start) = remaining.iter().next() {
+        remaining.remove(&start);
+        let mut stack = vec![start];
+        let mut size = 0;
+        while let Some((x, y)) = stack.pop() {
+            size += 1;
+            for next in [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)] {
+                if remaining.remove(&next) {
+                    stack.push(next);
+                }
+            }
+        }
+        largest = largest.max(size);
+    }
+    largest
+}
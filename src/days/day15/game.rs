@@ -0,0 +1,222 @@
+use std::io::{BufRead, Write};
+
+use super::{Direction, Input, Map};
+use crate::visualize::Frame;
+use crate::Result;
+
+const HELP: &str = "\
+Commands:
+  w a s d, arrow keys Move the robot, several moves can be typed on one line
+  n, next [n]         Replay the next n moves of the input (default 1)
+  u, undo [n]         Undo the last n moves (default 1)
+  t, toggle           Switch between the narrow and the wide warehouse, keeping the moves
+  reset               Start over in the current warehouse
+  h, help             Show this help
+  q, quit             Exit the game";
+
+pub struct Game {
+    initial: Map,
+    directions: Vec<Direction>,
+    wide: bool,
+    map: Map,
+    moves: Vec<(Direction, bool)>,
+    replayed: usize,
+}
+
+impl Game {
+    pub fn new(input: Input) -> Self {
+        Self {
+            map: input.map.clone(),
+            initial: input.map,
+            directions: input.directions,
+            wide: false,
+            moves: Vec::new(),
+            replayed: 0,
+        }
+    }
+
+    pub fn run(mut self, input: impl BufRead, mut output: impl Write) -> Result<()> {
+        write!(output, "{}{HELP}\n(day15) ", self.screen())?;
+        output.flush()?;
+        for line in input.lines() {
+            match self.execute(&line?) {
+                Some(response) => write!(output, "{}{response}\n(day15) ", self.screen())?,
+                None => break,
+            }
+            output.flush()?;
+        }
+        Ok(())
+    }
+
+    fn execute(&mut self, line: &str) -> Option<String> {
+        let words = line.split_whitespace().collect::<Vec<_>>();
+        Some(match words.as_slice() {
+            [] => String::new(),
+            ["n" | "next"] => self.next(1),
+            ["n" | "next", n] => match n.parse() {
+                Ok(n) => self.next(n),
+                Err(_) => format!("Invalid number of moves '{n}'"),
+            },
+            ["u" | "undo"] => self.undo(1),
+            ["u" | "undo", n] => match n.parse() {
+                Ok(n) => self.undo(n),
+                Err(_) => format!("Invalid number of moves '{n}'"),
+            },
+            ["t" | "toggle"] => {
+                self.wide = !self.wide;
+                self.rebuild();
+                String::new()
+            }
+            ["reset"] => {
+                self.moves.clear();
+                self.replayed = 0;
+                self.rebuild();
+                String::new()
+            }
+            ["h" | "help"] => HELP.to_string(),
+            ["q" | "quit"] => return None,
+            _ => match Self::parse_moves(line) {
+                Some(directions) => {
+                    for direction in directions {
+                        self.apply(direction, false);
+                    }
+                    String::new()
+                }
+                None => format!("Unknown command '{line}', type 'help' for a list of commands"),
+            },
+        })
+    }
+
+    /// Parses a line of WASD keys and arrow keys, which the terminal sends as escape sequences.
+    fn parse_moves(line: &str) -> Option<Vec<Direction>> {
+        let mut directions = Vec::new();
+        let mut chars = line.trim().chars();
+        while let Some(c) = chars.next() {
+            directions.push(match c {
+                'w' | 'W' => Direction::Up,
+                'a' | 'A' => Direction::Left,
+                's' | 'S' => Direction::Down,
+                'd' | 'D' => Direction::Right,
+                '\x1b' => match (chars.next(), chars.next()) {
+                    (Some('[' | 'O'), Some('A')) => Direction::Up,
+                    (Some('[' | 'O'), Some('B')) => Direction::Down,
+                    (Some('[' | 'O'), Some('C')) => Direction::Right,
+                    (Some('[' | 'O'), Some('D')) => Direction::Left,
+                    _ => return None,
+                },
+                c if c.is_whitespace() => continue,
+                _ => return None,
+            });
+        }
+        Some(directions)
+    }
+
+    fn apply(&mut self, direction: Direction, from_input: bool) {
+        if self.wide {
+            self.map.move_robot_part2(direction);
+        } else {
+            self.map.move_robot_part1(direction);
+        }
+        self.moves.push((direction, from_input));
+    }
+
+    fn next(&mut self, n: usize) -> String {
+        for _ in 0..n {
+            let Some(&direction) = self.directions.get(self.replayed) else {
+                return "All moves of the input are replayed".to_string();
+            };
+            self.apply(direction, true);
+            self.replayed += 1;
+        }
+        String::new()
+    }
+
+    fn undo(&mut self, n: usize) -> String {
+        if self.moves.is_empty() {
+            return "Nothing to undo".to_string();
+        }
+        for (_, from_input) in self.moves.drain(self.moves.len().saturating_sub(n)..) {
+            if from_input {
+                self.replayed -= 1;
+            }
+        }
+        self.rebuild();
+        String::new()
+    }
+
+    /// Moves the robot again from the start, after the warehouse changed or moves were undone.
+    fn rebuild(&mut self) {
+        self.map = self.initial.clone();
+        if self.wide {
+            self.map.convert_to_part2();
+        }
+        for (direction, from_input) in std::mem::take(&mut self.moves) {
+            self.apply(direction, from_input);
+        }
+    }
+
+    fn screen(&self) -> String {
+        format!(
+            "\x1b[2J\x1b[H{}GPS: {}  Moves: {}  Input: {}/{}  Warehouse: {}\n",
+            self.map.to_ansi(),
+            self.map.gps(),
+            self.moves.len(),
+            self.replayed,
+            self.directions.len(),
+            if self.wide { "wide" } else { "narrow" }
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::days::day15::{Parser, Position};
+
+    #[test]
+    fn replay_and_toggle() -> Result<()> {
+        let mut game = Game::new(Parser::parse(include_str!("example2.txt"))?);
+
+        assert_eq!(game.execute("next 700"), Some(String::new()));
+        assert_eq!(game.map.gps(), 10092);
+        assert_eq!(
+            game.execute("n 1000"),
+            Some("All moves of the input are replayed".to_string())
+        );
+        assert_eq!(game.execute("toggle"), Some(String::new()));
+        assert_eq!(game.map.gps(), 9021);
+        assert_eq!(game.execute("undo 10000"), Some(String::new()));
+        assert_eq!(game.replayed, 0);
+        let mut wide = game.initial.clone();
+        wide.convert_to_part2();
+        assert_eq!(game.map.gps(), wide.gps());
+        assert_eq!(game.execute("u"), Some("Nothing to undo".to_string()));
+        assert_eq!(game.execute("q"), None);
+        Ok(())
+    }
+
+    #[test]
+    fn moves_and_undo() -> Result<()> {
+        let mut game = Game::new(Parser::parse(include_str!("example1.txt"))?);
+        let position = |x, y| Position { x, y };
+        assert!(game.map.robot == position(2, 2));
+
+        assert_eq!(game.execute("w \x1b[A"), Some(String::new()));
+        assert!(game.map.robot == position(2, 1));
+        assert_eq!(game.execute("d\x1b[C"), Some(String::new()));
+        assert!(game.map.robot == position(4, 1));
+        assert_eq!(game.map.to_ascii().lines().nth(1), Some("#...@OO#"));
+        assert_eq!(game.execute("next"), Some(String::new()));
+        assert_eq!((game.moves.len(), game.replayed), (5, 1));
+        assert_eq!(game.execute("undo 3"), Some(String::new()));
+        assert_eq!((game.moves.len(), game.replayed), (2, 0));
+        assert!(game.map.robot == position(2, 1));
+        assert_eq!(game.execute("reset"), Some(String::new()));
+        assert!(game.map.robot == position(2, 2));
+        assert_eq!(
+            game.execute("x"),
+            Some("Unknown command 'x', type 'help' for a list of commands".to_string())
+        );
+        Ok(())
+    }
+}
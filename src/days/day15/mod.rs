@@ -1,4 +1,6 @@
-use std::collections::BTreeSet;
+mod game;
+
+use std::{collections::BTreeSet, io};
 
 use nom::{
     character::complete::{anychar, newline},
@@ -9,7 +11,9 @@ use nom::{
     Err,
 };
 
+use crate::visualize::{Cell, Frame, Rgb};
 use crate::*;
+use game::Game;
 
 day! {
     Output = usize,
@@ -31,6 +35,7 @@ enum Tile {
     Robot,
 }
 
+#[derive(Clone)]
 struct Map {
     tiles: Vec<Vec<Tile>>,
     robot: Position,
@@ -74,6 +79,18 @@ impl Position {
 }
 
 impl Map {
+    fn gps(&self) -> usize {
+        self.tiles
+            .iter()
+            .enumerate()
+            .flat_map(|(y, line)| {
+                line.iter().enumerate().filter_map(move |(x, &tile)| {
+                    (tile == Tile::Box || tile == Tile::BoxLeft).then_some(y * 100 + x)
+                })
+            })
+            .sum()
+    }
+
     fn move_robot_part1(&mut self, direction: Direction) {
         let next = self.robot.next(direction);
         let mut next_empty = next;
@@ -188,32 +205,45 @@ impl Map {
     }
 }
 
+impl Frame for Map {
+    fn size(&self) -> (usize, usize) {
+        (self.tiles[0].len(), self.tiles.len())
+    }
+
+    fn cell(&self, x: usize, y: usize) -> Cell {
+        match self.tiles[y][x] {
+            Tile::Empty => Cell::new('.', Rgb::BLACK),
+            Tile::Box => Cell::new('O', Rgb::BROWN),
+            Tile::BoxLeft => Cell::new('[', Rgb::BROWN),
+            Tile::BoxRight => Cell::new(']', Rgb::BROWN),
+            Tile::Wall => Cell::new('#', Rgb::GRAY),
+            Tile::Robot => Cell::new('@', Rgb::RED),
+        }
+    }
+}
+
 impl Day {
+    pub fn play() -> Result<()> {
+        Game::new(Parser::parse(include_str!("input.txt"))?).run(io::stdin().lock(), io::stdout())
+    }
+
     fn part1(mut input: Parsed) -> Result<Output> {
+        visualize::record(|| &input.map);
         for direction in input.directions {
             input.map.move_robot_part1(direction);
+            visualize::record(|| &input.map);
         }
-        Self::gps(input.map.tiles)
+        Ok(input.map.gps())
     }
 
     fn part2(mut input: Parsed) -> Result<Output> {
         input.map.convert_to_part2();
+        visualize::record(|| &input.map);
         for direction in input.directions {
             input.map.move_robot_part2(direction);
+            visualize::record(|| &input.map);
         }
-        Self::gps(input.map.tiles)
-    }
-
-    fn gps(tiles: Vec<Vec<Tile>>) -> Result<Output> {
-        Ok(tiles
-            .into_iter()
-            .enumerate()
-            .flat_map(|(y, line)| {
-                line.into_iter().enumerate().filter_map(move |(x, tile)| {
-                    (tile == Tile::Box || tile == Tile::BoxLeft).then_some(y * 100 + x)
-                })
-            })
-            .sum())
+        Ok(input.map.gps())
     }
 }
 
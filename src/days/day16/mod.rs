@@ -1,5 +1,6 @@
 use std::collections::BTreeSet;
 
+use anyhow::anyhow;
 use nom::{
     character::complete::{anychar, newline},
     combinator::{all_consuming, map, map_res},
@@ -9,6 +10,7 @@ use nom::{
 };
 use pathfinding::prelude::{astar, astar_bag};
 
+use crate::visualize::{Canvas, Cell, Frame, Rgb};
 use crate::*;
 
 day! {
@@ -50,6 +52,28 @@ struct Node {
     direction: Direction,
 }
 
+/// One of the cheapest routes through the maze, with every move and turn of the reindeer.
+struct Route {
+    nodes: Vec<Node>,
+    cost: usize,
+}
+
+impl Route {
+    fn steps(&self) -> usize {
+        self.nodes
+            .windows(2)
+            .filter(|nodes| nodes[0].position != nodes[1].position)
+            .count()
+    }
+
+    fn turns(&self) -> usize {
+        self.nodes
+            .windows(2)
+            .filter(|nodes| nodes[0].direction != nodes[1].direction)
+            .count()
+    }
+}
+
 impl From<Vec<Vec<Tile>>> for Map {
     fn from(tiles: Vec<Vec<Tile>>) -> Self {
         Self {
@@ -73,6 +97,46 @@ impl Map {
             .unwrap()
     }
 
+    fn best_route(&self) -> Result<Route> {
+        astar(
+            &self.start.into(),
+            |node| self.successors(node),
+            |node| self.heuristic(node),
+            |node| self.success(node),
+        )
+        .map(|(nodes, cost)| Route { nodes, cost })
+        .ok_or_else(|| anyhow!("There is no route from the start to the end"))
+    }
+
+    fn best_routes(&self) -> Result<Vec<Route>> {
+        let (solutions, cost) = astar_bag(
+            &self.start.into(),
+            |node| self.successors(node),
+            |node| self.heuristic(node),
+            |node| self.success(node),
+        )
+        .ok_or_else(|| anyhow!("There is no route from the start to the end"))?;
+        Ok(solutions.map(|nodes| Route { nodes, cost }).collect())
+    }
+
+    /// The maze with the heading of the reindeer drawn on every tile of the routes.
+    fn canvas(&self, routes: &[Route]) -> Result<Canvas> {
+        let mut canvas = Canvas::from_grid(&self.tiles, |tile| match tile {
+            Tile::Wall => Cell::new('#', Rgb::GRAY),
+            _ => Cell::new('.', Rgb::BLACK),
+        })?;
+        for node in routes.iter().flat_map(|route| &route.nodes) {
+            canvas.set(
+                node.position.x,
+                node.position.y,
+                Cell::new(node.direction.arrow(), Rgb::GREEN),
+            );
+        }
+        canvas.set(self.start.x, self.start.y, Cell::new('S', Rgb::YELLOW));
+        canvas.set(self.end.x, self.end.y, Cell::new('E', Rgb::RED));
+        Ok(canvas)
+    }
+
     fn successors(&self, node: &Node) -> Vec<(Node, usize)> {
         let mut successors = Vec::with_capacity(3);
         let mut next_position = node.position.next(node.direction);
@@ -146,6 +210,15 @@ impl Position {
 }
 
 impl Direction {
+    fn arrow(&self) -> char {
+        match self {
+            Direction::North => '^',
+            Direction::East => '>',
+            Direction::South => 'v',
+            Direction::West => '<',
+        }
+    }
+
     fn rotate_cw(&self) -> Direction {
         match self {
             Direction::North => Direction::East,
@@ -175,30 +248,51 @@ impl From<Position> for Node {
 }
 
 impl Day {
+    pub fn explain(part: Part) -> Result<String> {
+        Self::explanation(Parser::parse(include_str!("input.txt"))?, part)
+    }
+
     fn part1(map: Parsed) -> Result<Output> {
-        Ok(astar(
-            &map.start.into(),
-            |node| map.successors(node),
-            |node| map.heuristic(node),
-            |node| map.success(node),
-        )
-        .unwrap()
-        .1)
+        Ok(map.best_route()?.cost)
     }
 
     fn part2(map: Parsed) -> Result<Output> {
-        Ok(astar_bag(
-            &map.start.into(),
-            |node| map.successors(node),
-            |node| map.heuristic(node),
-            |node| map.success(node),
-        )
-        .unwrap()
-        .0
-        .flatten()
-        .map(|node| node.position)
-        .collect::<BTreeSet<_>>()
-        .len())
+        let routes = map.best_routes()?;
+        visualize::try_record(|| map.canvas(&routes));
+        Ok(Self::tiles(&routes))
+    }
+
+    fn tiles(routes: &[Route]) -> usize {
+        routes
+            .iter()
+            .flat_map(|route| route.nodes.iter().map(|node| node.position))
+            .collect::<BTreeSet<_>>()
+            .len()
+    }
+
+    fn explanation(map: Parsed, part: Part) -> Result<String> {
+        let routes = match part {
+            Part1 => vec![map.best_route()?],
+            Part2 => map.best_routes()?,
+        };
+        let mut explanation = map.canvas(&routes)?.to_ascii();
+        if part == Part2 {
+            explanation += &format!(
+                "{} best paths with a score of {}, visiting {} tiles\n",
+                routes.len(),
+                routes[0].cost,
+                Self::tiles(&routes)
+            );
+        }
+        for route in &routes {
+            explanation += &format!(
+                "Score {}: {} steps and {} turns\n",
+                route.cost,
+                route.steps(),
+                route.turns()
+            );
+        }
+        Ok(explanation)
     }
 }
 
@@ -239,4 +333,30 @@ mod tests {
     test_example!("example1", Part2, 45);
 
     test_example!("example2", Part2, 64);
+
+    #[test]
+    fn routes() -> Result<()> {
+        let map = Parser::parse(include_str!("example2.txt"))?;
+        let route = map.best_route()?;
+        assert_eq!(route.cost, route.steps() + 1000 * route.turns());
+        assert_eq!((route.steps(), route.turns()), (48, 11));
+
+        let routes = map.best_routes()?;
+        assert_eq!(routes.len(), 2);
+        assert!(routes
+            .iter()
+            .all(|route| route.cost == route.steps() + 1000 * route.turns()));
+        Ok(())
+    }
+
+    #[test]
+    fn explanation() -> Result<()> {
+        let explanation = Day::explanation(Parser::parse(include_str!("example1.txt"))?, Part1)?;
+        let lines = explanation.lines().collect::<Vec<_>>();
+        assert_eq!(lines[1], "#.......#....E#");
+        assert_eq!(lines[11], "#>>>>^#...#v#^#");
+        assert_eq!(lines[13], "#S..#.....#>>^#");
+        assert_eq!(lines[15], "Score 7036: 36 steps and 7 turns");
+        Ok(())
+    }
 }
@@ -0,0 +1,253 @@
+use std::{
+    collections::BTreeSet,
+    io::{BufRead, Write},
+};
+
+use super::{Device, Number};
+use crate::Result;
+
+const HELP: &str = "\
+Commands:
+  s, step [n]         Execute the next n instructions (default 1)
+  c, continue         Run until a breakpoint is hit or the program halts
+  b, break [ip]       Toggle a breakpoint at ip, or list all breakpoints
+  r, registers        Show the registers and the instruction pointer
+  set <a|b|c> <value> Set a register
+  l, list             Show the disassembled program
+  o, output           Show the output so far
+  reset               Restart the program, keeping the breakpoints
+  h, help             Show this help
+  q, quit             Exit the debugger";
+
+pub struct Debugger {
+    initial: Device,
+    device: Device,
+    breakpoints: BTreeSet<usize>,
+    output: Vec<Number>,
+}
+
+impl Debugger {
+    pub fn new(device: Device) -> Self {
+        Self {
+            initial: device.clone(),
+            device,
+            breakpoints: BTreeSet::new(),
+            output: Vec::new(),
+        }
+    }
+
+    pub fn run(mut self, input: impl BufRead, mut output: impl Write) -> Result<()> {
+        writeln!(output, "{}\n", self.list())?;
+        write!(output, "(day17) ")?;
+        output.flush()?;
+        for line in input.lines() {
+            match self.execute(&line?) {
+                Some(response) => writeln!(output, "{response}")?,
+                None => break,
+            }
+            write!(output, "(day17) ")?;
+            output.flush()?;
+        }
+        Ok(())
+    }
+
+    fn execute(&mut self, line: &str) -> Option<String> {
+        let words = line.split_whitespace().collect::<Vec<_>>();
+        Some(match words.as_slice() {
+            [] => String::new(),
+            ["s" | "step"] => self.step(1),
+            ["s" | "step", n] => match n.parse() {
+                Ok(n) => self.step(n),
+                Err(_) => format!("Invalid number of steps '{n}'"),
+            },
+            ["c" | "continue"] => self.continue_(),
+            ["b" | "break"] => format!("Breakpoints: {:?}", self.breakpoints),
+            ["b" | "break", ip] => match ip.parse() {
+                Ok(ip) if self.breakpoints.remove(&ip) => format!("Breakpoint removed at {ip}"),
+                Ok(ip) => {
+                    self.breakpoints.insert(ip);
+                    format!("Breakpoint set at {ip}")
+                }
+                Err(_) => format!("Invalid instruction pointer '{ip}'"),
+            },
+            ["r" | "registers"] => self.registers(),
+            ["set", register, value] => {
+                let index = match register.to_ascii_lowercase().as_str() {
+                    "a" => 0,
+                    "b" => 1,
+                    "c" => 2,
+                    _ => return Some(format!("Unknown register '{register}'")),
+                };
+                match value.parse() {
+                    Ok(value) => {
+                        self.device.registers[index] = value;
+                        self.registers()
+                    }
+                    Err(_) => format!("Invalid value '{value}'"),
+                }
+            }
+            ["l" | "list"] => self.list(),
+            ["o" | "output"] => self.output(),
+            ["reset"] => {
+                self.device = self.initial.clone();
+                self.output.clear();
+                self.registers()
+            }
+            ["h" | "help"] => HELP.to_string(),
+            ["q" | "quit"] => return None,
+            _ => format!("Unknown command '{line}', type 'help' for a list of commands"),
+        })
+    }
+
+    fn step(&mut self, n: usize) -> String {
+        let mut lines = Vec::new();
+        for step in self.device.trace().take(n) {
+            match step {
+                Ok(step) => {
+                    self.output.extend(step.output);
+                    lines.push(step.to_string());
+                }
+                Err(err) => {
+                    lines.push(format!("Error: {err}"));
+                    return lines.join("\n");
+                }
+            }
+        }
+        if lines.len() < n {
+            lines.push("Program halted".to_string());
+        }
+        lines.join("\n")
+    }
+
+    fn continue_(&mut self) -> String {
+        loop {
+            match self.device.step(&mut self.output) {
+                Ok(true) if self.breakpoints.contains(&self.device.ip) => {
+                    return format!("Breakpoint at {}\n{}", self.device.ip, self.registers());
+                }
+                Ok(true) => {}
+                Ok(false) => return format!("Program halted\n{}", self.output()),
+                Err(err) => return format!("Error: {err}\n{}", self.registers()),
+            }
+        }
+    }
+
+    fn registers(&self) -> String {
+        let [a, b, c] = self.device.registers;
+        format!("A={a} B={b} C={c} ip={}", self.device.ip)
+    }
+
+    fn list(&self) -> String {
+        self.device
+            .disassemble()
+            .into_iter()
+            .map(|(ip, line)| {
+                let marker = if ip == self.device.ip { '>' } else { ' ' };
+                let breakpoint = if self.breakpoints.contains(&ip) {
+                    '*'
+                } else {
+                    ' '
+                };
+                format!("{marker}{breakpoint}{ip:>3}: {line}")
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn output(&self) -> String {
+        format!(
+            "Output: {}",
+            self.output
+                .iter()
+                .map(Number::to_string)
+                .collect::<Vec<_>>()
+                .join(",")
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::days::day17::Parser;
+
+    #[test]
+    fn session() -> Result<()> {
+        let device = Parser::parse(include_str!("example1.txt"))?;
+        let mut debugger = Debugger::new(device);
+
+        assert_eq!(
+            debugger.execute("b 4"),
+            Some("Breakpoint set at 4".to_string())
+        );
+        assert_eq!(
+            debugger.execute("list"),
+            Some(">   0: adv 1\n    2: out A\n *  4: jnz 0".to_string())
+        );
+        assert_eq!(
+            debugger.execute("c"),
+            Some("Breakpoint at 4\nA=364 B=0 C=0 ip=4".to_string())
+        );
+        assert_eq!(debugger.execute("o"), Some("Output: 4".to_string()));
+        assert_eq!(
+            debugger.execute("s 2"),
+            Some("  4: jnz 0  A=364 B=0 C=0\n  0: adv 1  A=182 B=0 C=0".to_string())
+        );
+        assert_eq!(
+            debugger.execute("b 4"),
+            Some("Breakpoint removed at 4".to_string())
+        );
+        assert_eq!(
+            debugger.execute("continue"),
+            Some("Program halted\nOutput: 4,6,3,5,6,3,5,2,1,0".to_string())
+        );
+        assert_eq!(debugger.execute("step"), Some("Program halted".to_string()));
+        assert_eq!(
+            debugger.execute("set a 5"),
+            Some("A=5 B=0 C=0 ip=6".to_string())
+        );
+        assert_eq!(
+            debugger.execute("reset"),
+            Some("A=729 B=0 C=0 ip=0".to_string())
+        );
+        assert_eq!(debugger.execute("quit"), None);
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_operations() -> Result<()> {
+        let device =
+            Parser::parse("Register A: 1\nRegister B: 0\nRegister C: 0\n\nProgram: 0,5,0,7")?;
+        let mut debugger = Debugger::new(device);
+        assert_eq!(
+            debugger.execute("set b 18446744073709551615"),
+            Some("A=1 B=18446744073709551615 C=0 ip=0".to_string())
+        );
+        assert_eq!(
+            debugger.execute("s 2"),
+            Some(
+                "  0: adv B  A=0 B=18446744073709551615 C=0\nError: Invalid combo operand 7"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            debugger.execute("c"),
+            Some("Error: Invalid combo operand 7\nA=0 B=18446744073709551615 C=0 ip=2".to_string())
+        );
+        assert!(
+            Parser::parse("Register A: 1\nRegister B: 0\nRegister C: 0\n\nProgram: 8,0").is_err()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn repl() -> Result<()> {
+        let device = Parser::parse(include_str!("example1.txt"))?;
+        let mut output = Vec::new();
+        Debugger::new(device).run("s\nr\nq\n".as_bytes(), &mut output)?;
+        let output = String::from_utf8(output)?;
+        assert!(output.contains("(day17)   0: adv 1  A=364 B=0 C=0\n"));
+        assert!(output.contains("(day17) A=364 B=0 C=0 ip=2\n"));
+        Ok(())
+    }
+}
@@ -1,10 +1,18 @@
-use std::collections::BTreeSet;
+mod debugger;
+mod quine;
+
+use std::{fmt, io, iter};
+
+use anyhow::{anyhow, bail};
+
+use debugger::Debugger;
+use quine::find_quine;
 
 use nom::{
     branch::alt,
     bytes::complete::tag,
     character::complete::{newline, u64},
-    combinator::{all_consuming, map},
+    combinator::{all_consuming, map, verify},
     multi::separated_list1,
     sequence::{preceded, separated_pair, terminated, tuple},
 };
@@ -26,67 +34,234 @@ struct Device {
     ip: usize,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Instruction {
+    Adv,
+    Bxl,
+    Bst,
+    Jnz,
+    Bxc,
+    Out,
+    Bdv,
+    Cdv,
+}
+
+/// A single executed instruction: its ip and the registers after executing it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct TraceStep {
+    ip: usize,
+    instruction: Instruction,
+    operand: Number,
+    registers: [Number; 3],
+    output: Option<Number>,
+}
+
+impl TryFrom<Number> for Instruction {
+    type Error = anyhow::Error;
+
+    fn try_from(opcode: Number) -> Result<Self> {
+        Ok(match opcode {
+            0 => Instruction::Adv,
+            1 => Instruction::Bxl,
+            2 => Instruction::Bst,
+            3 => Instruction::Jnz,
+            4 => Instruction::Bxc,
+            5 => Instruction::Out,
+            6 => Instruction::Bdv,
+            7 => Instruction::Cdv,
+            _ => bail!("Invalid opcode {opcode}"),
+        })
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Instruction::Adv => write!(f, "adv"),
+            Instruction::Bxl => write!(f, "bxl"),
+            Instruction::Bst => write!(f, "bst"),
+            Instruction::Jnz => write!(f, "jnz"),
+            Instruction::Bxc => write!(f, "bxc"),
+            Instruction::Out => write!(f, "out"),
+            Instruction::Bdv => write!(f, "bdv"),
+            Instruction::Cdv => write!(f, "cdv"),
+        }
+    }
+}
+
+impl Instruction {
+    fn operand_name(self, operand: Number) -> String {
+        match self {
+            Instruction::Bxc => String::new(),
+            Instruction::Bxl | Instruction::Jnz => operand.to_string(),
+            Instruction::Adv
+            | Instruction::Bst
+            | Instruction::Out
+            | Instruction::Bdv
+            | Instruction::Cdv => match operand {
+                0..=3 => operand.to_string(),
+                4 => "A".to_string(),
+                5 => "B".to_string(),
+                6 => "C".to_string(),
+                _ => "?".to_string(),
+            },
+        }
+    }
+}
+
+impl fmt::Display for TraceStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:>3}: {:<6} A={} B={} C={}",
+            self.ip,
+            format!(
+                "{} {}",
+                self.instruction,
+                self.instruction.operand_name(self.operand)
+            ),
+            self.registers[0],
+            self.registers[1],
+            self.registers[2]
+        )?;
+        if let Some(output) = self.output {
+            write!(f, " out={output}")?;
+        }
+        Ok(())
+    }
+}
+
 impl Device {
-    fn run(&mut self) -> Vec<Number> {
+    fn run(&mut self) -> Result<Vec<Number>> {
         let mut output = Vec::new();
-        while let Some((instruction, operand)) = self.get_operation() {
-            match instruction {
-                0 => {
-                    self.registers[0] /= (2 as Number).pow(self.combo(operand) as u32);
-                }
-                1 => {
-                    self.registers[1] ^= operand;
-                }
-                2 => {
-                    self.registers[1] = self.combo(operand) % 8;
-                }
-                3 => {
-                    if self.registers[0] != 0 {
-                        self.ip = operand;
-                    }
-                }
-                4 => {
-                    self.registers[1] ^= self.registers[2];
-                }
-                5 => output.push(self.combo(operand) % 8),
-                6 => {
-                    self.registers[1] =
-                        self.registers[0] / (2 as Number).pow(self.combo(operand) as u32);
-                }
-                7 => {
-                    self.registers[2] =
-                        self.registers[0] / (2 as Number).pow(self.combo(operand) as u32);
+        while self.step(&mut output)? {}
+        Ok(output)
+    }
+
+    /// Executes the next instruction, and returns whether there was one. An invalid
+    /// instruction is an error, leaving the device as it was.
+    fn step(&mut self, output: &mut Vec<Number>) -> Result<bool> {
+        let Some((instruction, operand)) = self.peek_operation()? else {
+            return Ok(false);
+        };
+        let mut ip = self.ip + 2;
+        match instruction {
+            Instruction::Adv => {
+                self.registers[0] = self.divide(operand)?;
+            }
+            Instruction::Bxl => {
+                self.registers[1] ^= operand;
+            }
+            Instruction::Bst => {
+                self.registers[1] = self.combo(operand)? % 8;
+            }
+            Instruction::Jnz => {
+                if self.registers[0] != 0 {
+                    ip = operand;
                 }
-                _ => unreachable!(),
+            }
+            Instruction::Bxc => {
+                self.registers[1] ^= self.registers[2];
+            }
+            Instruction::Out => output.push(self.combo(operand)? % 8),
+            Instruction::Bdv => {
+                self.registers[1] = self.divide(operand)?;
+            }
+            Instruction::Cdv => {
+                self.registers[2] = self.divide(operand)?;
             }
         }
-        output
+        self.ip = ip;
+        Ok(true)
     }
 
-    fn get_operation(&mut self) -> Option<(Number, Number)> {
-        if self.ip <= self.program.len() - 2 {
+    /// Register A divided by two to the power of the combo operand. Shifting all bits out
+    /// leaves zero instead of overflowing.
+    fn divide(&self, operand: Number) -> Result<Number> {
+        let shift = self.combo(operand)?;
+        Ok(u32::try_from(shift)
+            .ok()
+            .and_then(|shift| self.registers[0].checked_shr(shift))
+            .unwrap_or(0))
+    }
+
+    /// Executes instructions until the program halts, stopping after the first error.
+    fn trace(&mut self) -> impl Iterator<Item = Result<TraceStep>> + use<'_> {
+        let mut output = Vec::new();
+        let mut failed = false;
+        iter::from_fn(move || {
+            if failed {
+                return None;
+            }
             let ip = self.ip;
-            self.ip += 2;
+            let step = self.peek_operation().and_then(|operation| {
+                let Some((instruction, operand)) = operation else {
+                    return Ok(None);
+                };
+                self.step(&mut output)?;
+                Ok(Some(TraceStep {
+                    ip,
+                    instruction,
+                    operand,
+                    registers: self.registers,
+                    output: output.pop(),
+                }))
+            });
+            failed = step.is_err();
+            step.transpose()
+        })
+    }
+
+    fn disassemble(&self) -> Vec<(usize, String)> {
+        self.program
+            .chunks(2)
+            .enumerate()
+            .map(|(index, chunk)| {
+                let line = match chunk {
+                    &[opcode, operand] => match Instruction::try_from(opcode) {
+                        Ok(instruction) => {
+                            format!("{instruction} {}", instruction.operand_name(operand))
+                                .trim_end()
+                                .to_string()
+                        }
+                        Err(_) => format!("{chunk:?}"),
+                    },
+                    _ => format!("{chunk:?}"),
+                };
+                (index * 2, line)
+            })
+            .collect()
+    }
 
-            Some((self.program[ip], self.program[ip + 1]))
+    fn peek_operation(&self) -> Result<Option<(Instruction, Number)>> {
+        if self.ip.saturating_add(2) <= self.program.len() {
+            Ok(Some((
+                Instruction::try_from(self.program[self.ip])?,
+                self.program[self.ip + 1],
+            )))
         } else {
-            None
+            Ok(None)
         }
     }
 
-    fn combo(&self, operand: Number) -> Number {
+    fn combo(&self, operand: Number) -> Result<Number> {
         match operand {
-            0..=3 => operand,
-            4..=6 => self.registers[operand - 4],
-            _ => unreachable!(),
+            0..=3 => Ok(operand),
+            4..=6 => Ok(self.registers[operand - 4]),
+            _ => Err(anyhow!("Invalid combo operand {operand}")),
         }
     }
 }
 
 impl Day {
+    pub fn debug() -> Result<()> {
+        Debugger::new(Parser::parse(include_str!("input.txt"))?)
+            .run(io::stdin().lock(), io::stdout())
+    }
+
     fn part1(mut device: Parsed) -> Result<Output> {
         Ok(device
-            .run()
+            .run()?
             .iter()
             .map(Number::to_string)
             .collect::<Vec<_>>()
@@ -94,25 +269,7 @@ impl Day {
     }
 
     fn part2(device: Parsed) -> Result<Output> {
-        let mut prev = BTreeSet::from([0]);
-        let mut next = BTreeSet::new();
-        for i in 0..device.program.len() {
-            for prefix in &prev {
-                for j in 0..8usize {
-                    let mut d = device.clone();
-                    let register_a = (prefix << 3) + j;
-                    d.registers[0] = register_a;
-                    let output = d.run();
-                    if output.len() == i + 1 && device.program.ends_with(&output) {
-                        next.insert(register_a);
-                    }
-                }
-            }
-            prev.clear();
-            prev.append(&mut next);
-        }
-
-        Ok(prev.first().unwrap().to_string())
+        Ok(find_quine(&device)?.to_string())
     }
 }
 
@@ -156,7 +313,7 @@ impl Parser {
     fn program(s: &'static str) -> IResult<Vec<Number>> {
         preceded(
             tag("Program: "),
-            separated_list1(tag(","), map(u64, |n| n as Number)),
+            separated_list1(tag(","), map(verify(u64, |&n| n < 8), |n| n as Number)),
         )(s)
     }
 }
@@ -172,4 +329,32 @@ mod tests {
     test_example!("example1", Part1, "4,6,3,5,6,3,5,2,1,0");
 
     test_example!("example2", Part2, 117440);
+
+    #[test]
+    fn disassemble() -> Result<()> {
+        let device = Parser::parse(include_str!("example1.txt"))?;
+        assert_eq!(
+            device.disassemble(),
+            vec![
+                (0, "adv 1".to_string()),
+                (2, "out A".to_string()),
+                (4, "jnz 0".to_string())
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn trace() -> Result<()> {
+        let mut device = Parser::parse(include_str!("example2.txt"))?;
+        let trace = device.trace().take(4).collect::<Result<Vec<_>>>()?;
+        assert_eq!(
+            trace.iter().map(|step| step.ip).collect::<Vec<_>>(),
+            vec![0, 2, 4, 0]
+        );
+        assert_eq!(trace[0].registers, [253, 0, 0]);
+        assert_eq!(trace[1].output, Some(5));
+        assert_eq!(trace[3].to_string(), "  0: adv 3  A=31 B=0 C=0");
+        Ok(())
+    }
 }
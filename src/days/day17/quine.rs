@@ -0,0 +1,208 @@
+use std::collections::BTreeSet;
+
+use anyhow::{anyhow, bail};
+
+use super::{Device, Instruction, Number};
+use crate::Result;
+
+/// The shape of a program that loops over register A: every iteration outputs one value,
+/// shifts A right by `shift` bits and derives B and C from A only. The output for A is
+/// then the output of one iteration followed by the output for `A >> shift`.
+#[derive(Debug, PartialEq, Eq)]
+struct LoopShape {
+    shift: u32,
+}
+
+impl LoopShape {
+    fn analyse(program: &[Number]) -> Result<Self> {
+        let chunks = program.chunks_exact(2);
+        if !chunks.remainder().is_empty() {
+            bail!("Program has an odd number of values");
+        }
+        if let Some(opcode) = program.iter().step_by(2).find(|&&opcode| opcode > 7) {
+            bail!("Program contains invalid opcode {opcode}");
+        }
+
+        let instructions = chunks
+            .map(|chunk| Ok((Instruction::try_from(chunk[0])?, chunk[1])))
+            .collect::<Result<Vec<_>>>()?;
+        let body = match instructions.split_last() {
+            Some((&(Instruction::Jnz, 0), body)) => body,
+            _ => bail!("Program does not end with a jump to the start"),
+        };
+
+        if body
+            .iter()
+            .any(|&(instruction, _)| instruction == Instruction::Jnz)
+        {
+            bail!("Program contains more than one jump");
+        }
+
+        let outputs = body
+            .iter()
+            .filter(|&&(instruction, _)| instruction == Instruction::Out)
+            .count();
+        if outputs != 1 {
+            bail!("Program outputs {outputs} values per iteration instead of 1");
+        }
+
+        let shift = match body
+            .iter()
+            .filter(|&&(instruction, _)| instruction == Instruction::Adv)
+            .collect::<Vec<_>>()
+            .as_slice()
+        {
+            [] | [(_, 0)] => bail!("Program does not shift register A"),
+            [(_, operand @ 1..=3)] => *operand as u32,
+            [_] => bail!("Program shifts register A by a register instead of a constant"),
+            advs => bail!(
+                "Program shifts register A {} times per iteration",
+                advs.len()
+            ),
+        };
+
+        let mut written = [true, false, false];
+        for &(instruction, operand) in body {
+            let mut reads = Vec::new();
+            match instruction {
+                Instruction::Adv
+                | Instruction::Bst
+                | Instruction::Out
+                | Instruction::Bdv
+                | Instruction::Cdv => match operand {
+                    0..=4 => {}
+                    5 | 6 => reads.push(operand - 4),
+                    _ => bail!("Program uses reserved combo operand {operand}"),
+                },
+                Instruction::Bxl => reads.push(1),
+                Instruction::Bxc => reads.extend([1, 2]),
+                Instruction::Jnz => {}
+            }
+            if let Some(&register) = reads.iter().find(|&&register| !written[register]) {
+                bail!(
+                    "Register {} carries state between iterations",
+                    ['A', 'B', 'C'][register]
+                );
+            }
+            match instruction {
+                Instruction::Bst | Instruction::Bdv => written[1] = true,
+                Instruction::Cdv => written[2] = true,
+                _ => {}
+            }
+        }
+
+        Ok(Self { shift })
+    }
+}
+
+/// Finds the lowest value for register A that makes the program output a copy of itself,
+/// by building A from the last output backwards, `shift` bits per output.
+pub fn find_quine(device: &Device) -> Result<Number> {
+    let LoopShape { shift } = LoopShape::analyse(&device.program)?;
+
+    let mut scratch = device.clone();
+    let mut output = Vec::with_capacity(1);
+    let mut first_output = |register_a| {
+        scratch.registers = [register_a, 0, 0];
+        scratch.ip = 0;
+        output.clear();
+        while output.is_empty() && scratch.step(&mut output)? {}
+        Ok::<_, anyhow::Error>(output.first().copied())
+    };
+
+    let mut candidates = BTreeSet::<Number>::from([0]);
+    for &expected in device.program.iter().rev() {
+        let mut next = BTreeSet::new();
+        for prefix in candidates {
+            if prefix.leading_zeros() < shift {
+                continue;
+            }
+            for j in 0..1 << shift {
+                let register_a = (prefix << shift) + j;
+                if register_a != 0 && first_output(register_a)? == Some(expected) {
+                    next.insert(register_a);
+                }
+            }
+        }
+        candidates = next;
+    }
+
+    candidates
+        .first()
+        .copied()
+        .ok_or_else(|| anyhow!("No value for register A makes the program output itself"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn analyse() {
+        assert_eq!(
+            LoopShape::analyse(&[2, 4, 1, 1, 7, 5, 0, 3, 4, 0, 1, 6, 5, 5, 3, 0]).ok(),
+            Some(LoopShape { shift: 3 })
+        );
+        assert_eq!(
+            LoopShape::analyse(&[0, 1, 5, 4, 3, 0]).ok(),
+            Some(LoopShape { shift: 1 })
+        );
+    }
+
+    #[test]
+    fn unsupported_shapes() {
+        for (program, error) in [
+            (
+                &[0, 3, 5, 4][..],
+                "Program does not end with a jump to the start",
+            ),
+            (
+                &[0, 3, 3, 0, 5, 4, 3, 0],
+                "Program contains more than one jump",
+            ),
+            (
+                &[0, 3, 3, 2],
+                "Program does not end with a jump to the start",
+            ),
+            (
+                &[0, 3, 3, 0],
+                "Program outputs 0 values per iteration instead of 1",
+            ),
+            (&[5, 4, 3, 0], "Program does not shift register A"),
+            (
+                &[0, 5, 5, 4, 3, 0],
+                "Program shifts register A by a register instead of a constant",
+            ),
+            (
+                &[0, 1, 0, 2, 5, 4, 3, 0],
+                "Program shifts register A 2 times per iteration",
+            ),
+            (
+                &[1, 3, 0, 3, 5, 5, 3, 0],
+                "Register B carries state between iterations",
+            ),
+            (
+                &[2, 4, 4, 0, 0, 3, 5, 5, 3, 0],
+                "Register C carries state between iterations",
+            ),
+            (&[0, 3, 5, 7, 3, 0], "Program uses reserved combo operand 7"),
+            (&[0, 3, 5], "Program has an odd number of values"),
+        ] {
+            assert_eq!(
+                LoopShape::analyse(program).unwrap_err().to_string(),
+                error,
+                "{program:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn no_quine() {
+        let device = Device {
+            registers: [0; 3],
+            program: vec![0, 1, 5, 4, 3, 0],
+            ip: 0,
+        };
+        assert!(find_quine(&device).is_err());
+    }
+}
@@ -0,0 +1,171 @@
+use std::collections::VecDeque;
+
+use anyhow::anyhow;
+
+use super::{Config, Position};
+use crate::visualize::{self, Canvas, Cell, Rgb};
+use crate::Result;
+
+/// Finds the index of the first byte that cuts off the exit by letting all bytes fall and then
+/// removing them again in reverse order, joining the free regions with a union-find.
+pub fn union_find(coordinates: &[Position], config: &Config) -> Result<usize> {
+    let size = config.size;
+    let mut fallen = vec![None; size * size];
+    for (index, position) in coordinates.iter().enumerate() {
+        fallen[position.y * size + position.x].get_or_insert(index);
+    }
+
+    let mut regions = UnionFind::new(size * size);
+    let free = |fallen: &[Option<usize>], tile: usize| fallen[tile].is_none();
+    for tile in 0..size * size {
+        if free(&fallen, tile) {
+            for neighbour in neighbours(tile, size) {
+                if free(&fallen, neighbour) {
+                    regions.union(tile, neighbour);
+                }
+            }
+        }
+    }
+
+    let (start, end) = (0, size * size - 1);
+    let connected = |fallen: &[Option<usize>], regions: &mut UnionFind| {
+        free(fallen, start) && free(fallen, end) && regions.find(start) == regions.find(end)
+    };
+    if connected(&fallen, &mut regions) {
+        return Err(anyhow!(
+            "The exit stays reachable after all bytes have fallen"
+        ));
+    }
+
+    for (index, position) in coordinates.iter().enumerate().rev() {
+        let tile = position.y * size + position.x;
+        if fallen[tile] != Some(index) {
+            continue;
+        }
+        fallen[tile] = None;
+        for neighbour in neighbours(tile, size) {
+            if free(&fallen, neighbour) {
+                regions.union(tile, neighbour);
+            }
+        }
+        visualize::record(|| canvas(&fallen, &mut regions, size));
+        if connected(&fallen, &mut regions) {
+            if index < config.coordinates {
+                break;
+            }
+            return Ok(index);
+        }
+    }
+    Err(anyhow!("The exit can not be reached"))
+}
+
+/// The fallen bytes, with the free tiles that can be reached from the start.
+fn canvas(fallen: &[Option<usize>], regions: &mut UnionFind, size: usize) -> Canvas {
+    let mut canvas = Canvas::new(size, size, Cell::new('.', Rgb::BLACK));
+    let start = regions.find(0);
+    for (tile, byte) in fallen.iter().enumerate() {
+        if byte.is_some() {
+            canvas.set(tile % size, tile / size, Cell::new('#', Rgb::RED));
+        } else if fallen[0].is_none() && regions.find(tile) == start {
+            canvas.set(tile % size, tile / size, Cell::new('O', Rgb::GREEN));
+        }
+    }
+    canvas
+}
+
+/// Finds the index of the first byte that cuts off the exit with a binary search on the number
+/// of fallen bytes, checking whether the exit can be reached with a breadth-first search.
+pub fn binary_search(coordinates: &[Position], config: &Config) -> Result<usize> {
+    // The exit is reachable after `low` bytes and not after `high` bytes.
+    let (mut low, mut high) = (config.coordinates.min(coordinates.len()), coordinates.len());
+    if !reachable(&coordinates[..low], config.size) {
+        return Err(anyhow!("The exit can not be reached"));
+    }
+    if reachable(coordinates, config.size) {
+        return Err(anyhow!(
+            "The exit stays reachable after all bytes have fallen"
+        ));
+    }
+
+    while high - low > 1 {
+        let middle = low + (high - low) / 2;
+        if reachable(&coordinates[..middle], config.size) {
+            low = middle;
+        } else {
+            high = middle;
+        }
+    }
+    Ok(high - 1)
+}
+
+fn reachable(coordinates: &[Position], size: usize) -> bool {
+    let mut blocked = vec![false; size * size];
+    for position in coordinates {
+        blocked[position.y * size + position.x] = true;
+    }
+
+    let end = size * size - 1;
+    let mut queue = VecDeque::new();
+    if !blocked[0] {
+        blocked[0] = true;
+        queue.push_back(0);
+    }
+    while let Some(tile) = queue.pop_front() {
+        if tile == end {
+            return true;
+        }
+        for neighbour in neighbours(tile, size) {
+            if !blocked[neighbour] {
+                blocked[neighbour] = true;
+                queue.push_back(neighbour);
+            }
+        }
+    }
+    false
+}
+
+pub fn neighbours(tile: usize, size: usize) -> impl Iterator<Item = usize> {
+    let (x, y) = (tile % size, tile / size);
+    [
+        (x > 0).then(|| tile - 1),
+        (x + 1 < size).then_some(tile + 1),
+        (y > 0).then(|| tile - size),
+        (y + 1 < size).then_some(tile + size),
+    ]
+    .into_iter()
+    .flatten()
+}
+
+struct UnionFind {
+    parents: Vec<usize>,
+    sizes: Vec<usize>,
+}
+
+impl UnionFind {
+    fn new(len: usize) -> Self {
+        Self {
+            parents: (0..len).collect(),
+            sizes: vec![1; len],
+        }
+    }
+
+    fn find(&mut self, mut node: usize) -> usize {
+        while self.parents[node] != node {
+            self.parents[node] = self.parents[self.parents[node]];
+            node = self.parents[node];
+        }
+        node
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (mut a, mut b) = (self.find(a), self.find(b));
+        if a == b {
+            return;
+        }
+        if self.sizes[a] < self.sizes[b] {
+            (a, b) = (b, a);
+        }
+        self.parents[b] = a;
+        self.sizes[a] += self.sizes[b];
+    }
+}
@@ -1,15 +1,18 @@
+mod blocking;
 mod util;
+use anyhow::anyhow;
 use pathfinding::prelude::astar;
 use util::*;
 
 use nom::{
     bytes::complete::tag,
-    character::complete::{newline, u8},
+    character::complete::{newline, u64},
     combinator::{all_consuming, map},
     multi::separated_list1,
     sequence::separated_pair,
 };
 
+use crate::visualize::{Canvas, Cell, Frame, Rgb};
 use crate::*;
 
 day! {
@@ -20,6 +23,37 @@ day! {
 struct Config {
     size: usize,
     coordinates: usize,
+    strategy: Strategy,
+}
+
+/// How part 2 searches for the first byte that cuts off the exit. All strategies give the same
+/// answer, the slower ones are kept to cross-check the others.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Strategy {
+    /// Find a new shortest path every time a byte falls on the previous one
+    Incremental,
+    /// Binary search on the number of fallen bytes
+    BinarySearch,
+    /// Remove the bytes again in reverse order, joining the free regions
+    #[default]
+    UnionFind,
+}
+
+impl Config {
+    fn check(&self, coordinates: &[Position]) -> Result<()> {
+        match coordinates
+            .iter()
+            .find(|position| position.x >= self.size || position.y >= self.size)
+        {
+            Some(position) => Err(anyhow!(
+                "Byte {},{} falls outside a memory space of size {}",
+                position.x,
+                position.y,
+                self.size
+            )),
+            None => Ok(()),
+        }
+    }
 }
 
 #[derive(Default, PartialEq, Eq, Hash, Clone, Copy)]
@@ -30,102 +64,189 @@ enum Tile {
 }
 
 struct MemorySpace {
-    bytes: Grid<71, 71, Tile>,
+    size: usize,
+    bytes: Vec<Tile>,
 }
 
 impl MemorySpace {
     fn with_size(size: usize) -> Self {
         Self {
-            bytes: Grid::with_size(size, size),
+            size,
+            bytes: vec![Tile::Empty; size * size],
         }
     }
-}
 
-impl Day {
-    fn part1(coordinates: Parsed) -> Result<Output> {
-        let config = Self::get_config(&coordinates);
-        let mut memory_space = MemorySpace::with_size(config.size);
-        for position in coordinates.iter().copied().take(config.coordinates) {
-            memory_space.bytes[position] = Tile::Corrupted;
-        }
+    fn corrupt(&mut self, position: Position) {
+        self.bytes[position.y * self.size + position.x] = Tile::Corrupted;
+    }
 
-        let start = memory_space.bytes.position(Position::default());
-        let end = memory_space.bytes.position(Position {
-            x: config.size - 1,
-            y: config.size - 1,
-        });
-        let (_, length) = astar(
-            &start,
-            |pos| {
-                [pos.left(), pos.right(), pos.up(), pos.down()]
-                    .into_iter()
-                    .filter_map(|next| {
-                        if let Some(next_pos) = next {
-                            if memory_space.bytes[next_pos] != Tile::Corrupted {
-                                return Some((next_pos, 1));
-                            }
-                        }
-                        None
-                    })
+    fn position(&self, tile: usize) -> Position {
+        Position::from((tile % self.size, tile / self.size))
+    }
+
+    /// The tiles of a shortest path from the top left to `end`, including both ends.
+    fn shortest_path(&self, end: Position) -> Option<Vec<Position>> {
+        let end = end.y * self.size + end.x;
+        astar(
+            &0,
+            |&tile| {
+                blocking::neighbours(tile, self.size)
+                    .filter(|&next| self.bytes[next] != Tile::Corrupted)
+                    .map(|next| (next, 1))
             },
-            |pos| pos.manhatten_distance(end),
-            |pos| *pos == end,
+            |&tile| self.position(tile).manhatten_distance(self.position(end)),
+            |&tile| tile == end,
         )
-        .unwrap();
+        .map(|(path, _)| path.into_iter().map(|tile| self.position(tile)).collect())
+    }
 
-        Ok(length.to_string())
+    fn canvas(&self, path: &[Position]) -> Canvas {
+        let mut canvas = Canvas::new(self.size, self.size, Cell::new('.', Rgb::BLACK));
+        for (tile, _) in self
+            .bytes
+            .iter()
+            .enumerate()
+            .filter(|(_, &tile)| tile == Tile::Corrupted)
+        {
+            let position = self.position(tile);
+            canvas.set(position.x, position.y, Cell::new('#', Rgb::RED));
+        }
+        for position in path {
+            canvas.set(position.x, position.y, Cell::new('O', Rgb::GREEN));
+        }
+        canvas
+    }
+}
+
+/// The first byte that cuts off the exit, with the last path that was still possible.
+struct Blocked {
+    byte: Position,
+    memory_space: MemorySpace,
+    last_path: Vec<Position>,
+}
+
+fn turns(path: &[Position]) -> usize {
+    path.windows(3)
+        .filter(|tiles| {
+            (tiles[0].x + tiles[2].x != 2 * tiles[1].x)
+                || (tiles[0].y + tiles[2].y != 2 * tiles[1].y)
+        })
+        .count()
+}
+
+impl Day {
+    pub fn explain(part: Part) -> Result<String> {
+        Self::explanation(Parser::parse(include_str!("input.txt"))?, part)
+    }
+
+    fn part1(coordinates: Parsed) -> Result<Output> {
+        let config = Self::get_config(&coordinates);
+        let (_, path) = Self::fallen(&coordinates, &config)?;
+        Ok((path.len() - 1).to_string())
     }
 
     fn part2(coordinates: Parsed) -> Result<Output> {
         let config = Self::get_config(&coordinates);
+        let byte = Self::first_blocking(&coordinates, &config)?;
+        Ok(format!("{},{}", byte.x, byte.y))
+    }
+
+    pub fn blocking(strategy: Strategy) -> Result<String> {
+        let coordinates = Parser::parse(include_str!("input.txt"))?;
+        let config = Config {
+            strategy,
+            ..Self::get_config(&coordinates)
+        };
+        let byte = Self::first_blocking(&coordinates, &config)?;
+        Ok(format!("{},{}", byte.x, byte.y))
+    }
+
+    fn first_blocking(coordinates: &Parsed, config: &Config) -> Result<Position> {
+        config.check(coordinates)?;
+        Ok(match config.strategy {
+            Strategy::Incremental => Self::blocked(coordinates, config)?.byte,
+            Strategy::BinarySearch => coordinates[blocking::binary_search(coordinates, config)?],
+            Strategy::UnionFind => coordinates[blocking::union_find(coordinates, config)?],
+        })
+    }
+
+    /// The memory space after the first bytes have fallen, with a shortest path to the exit.
+    fn fallen(coordinates: &Parsed, config: &Config) -> Result<(MemorySpace, Vec<Position>)> {
+        config.check(coordinates)?;
         let mut memory_space = MemorySpace::with_size(config.size);
-        let mut coordinates_iter = coordinates.iter().copied();
-        for _ in 0..config.coordinates {
-            let position = coordinates_iter.next().unwrap();
-            memory_space.bytes[position] = Tile::Corrupted;
+        for position in coordinates.iter().copied().take(config.coordinates) {
+            memory_space.corrupt(position);
         }
 
-        let end = Position {
-            x: config.size - 1,
-            y: config.size - 1,
-        };
+        let path = memory_space
+            .shortest_path(Self::exit(config))
+            .ok_or_else(|| anyhow!("The exit can not be reached"))?;
+        Ok((memory_space, path))
+    }
 
-        let mut last_path = Vec::new();
+    fn blocked(coordinates: &Parsed, config: &Config) -> Result<Blocked> {
+        let (mut memory_space, mut last_path) = Self::fallen(coordinates, config)?;
+        let end = Self::exit(config);
 
-        #[allow(clippy::while_let_on_iterator)]
-        while let Some(position) = coordinates_iter.next() {
-            memory_space.bytes[position] = Tile::Corrupted;
+        for &byte in coordinates.iter().skip(config.coordinates) {
+            memory_space.corrupt(byte);
 
-            if !last_path.is_empty() && last_path.iter().copied().all(|pos| pos != position) {
+            if last_path.iter().copied().all(|pos| pos != byte) {
                 continue;
             }
 
-            let start_pos = memory_space.bytes.position(Position::default());
-            let end_pos = memory_space.bytes.position(end);
-
-            if let Some((path, _)) = astar(
-                &start_pos,
-                |pos| {
-                    [pos.left(), pos.right(), pos.up(), pos.down()]
-                        .into_iter()
-                        .filter_map(|next| {
-                            if let Some(next_pos) = next {
-                                if memory_space.bytes[next_pos] != Tile::Corrupted {
-                                    return Some((next_pos, 1));
-                                }
-                            }
-                            None
-                        })
-                },
-                |pos| pos.manhatten_distance(end_pos),
-                |pos| *pos == end_pos,
-            ) {
-                last_path = path.into_iter().map(|pos| pos.position()).collect();
-            } else {
-                return Ok(format!("{},{}", position.x, position.y));
+            match memory_space.shortest_path(end) {
+                Some(path) => {
+                    last_path = path;
+                    visualize::record(|| memory_space.canvas(&last_path));
+                }
+                None => {
+                    return Ok(Blocked {
+                        byte,
+                        memory_space,
+                        last_path,
+                    })
+                }
+            }
+        }
+        Err(anyhow!(
+            "The exit stays reachable after all bytes have fallen"
+        ))
+    }
+
+    fn explanation(coordinates: Parsed, part: Part) -> Result<String> {
+        let config = Self::get_config(&coordinates);
+        Ok(match part {
+            Part1 => {
+                let (memory_space, path) = Self::fallen(&coordinates, &config)?;
+                format!(
+                    "{}{} steps and {} turns\n",
+                    memory_space.canvas(&path).to_ascii(),
+                    path.len() - 1,
+                    turns(&path)
+                )
             }
+            Part2 => {
+                let blocked = Self::blocked(&coordinates, &config)?;
+                let mut canvas = blocked.memory_space.canvas(&blocked.last_path);
+                canvas.set(blocked.byte.x, blocked.byte.y, Cell::new('X', Rgb::YELLOW));
+                format!(
+                    "{}Byte {},{} blocks the last path of {} steps and {} turns\n",
+                    canvas.to_ascii(),
+                    blocked.byte.x,
+                    blocked.byte.y,
+                    blocked.last_path.len() - 1,
+                    turns(&blocked.last_path)
+                )
+            }
+        })
+    }
+
+    fn exit(config: &Config) -> Position {
+        Position {
+            x: config.size - 1,
+            y: config.size - 1,
         }
-        unreachable!()
     }
 
     fn get_config(coordinates: &Parsed) -> Config {
@@ -133,11 +254,13 @@ impl Day {
             Config {
                 size: 7,
                 coordinates: 12,
+                strategy: Strategy::default(),
             }
         } else {
             Config {
                 size: 71,
                 coordinates: 1024,
+                strategy: Strategy::default(),
             }
         }
     }
@@ -145,7 +268,9 @@ impl Day {
 
 impl Parser {
     fn parse(input: &'static str) -> Result<Parsed> {
-        Ok(all_consuming(Self::coordinates)(input)?.1)
+        let coordinates = all_consuming(Self::coordinates)(input)?.1;
+        Day::get_config(&coordinates).check(&coordinates)?;
+        Ok(coordinates)
     }
 
     fn coordinates(s: &'static str) -> IResult<Parsed> {
@@ -153,7 +278,7 @@ impl Parser {
     }
 
     fn coordinate(s: &'static str) -> IResult<Position> {
-        map(separated_pair(u8, tag(","), u8), |(x, y)| Position {
+        map(separated_pair(u64, tag(","), u64), |(x, y)| Position {
             x: x as usize,
             y: y as usize,
         })(s)
@@ -171,4 +296,108 @@ mod tests {
     test_example!("example1", Part1, 22);
 
     test_example!("example1", Part2, "6,1");
+
+    #[test]
+    fn explanation() -> Result<()> {
+        let coordinates = Parser::parse(include_str!("example1.txt"))?;
+        let explanation = Day::explanation(coordinates.clone(), Part1)?;
+        assert!(explanation.starts_with("OO.#OOO\n.O#OO#O\n"));
+        assert_eq!(explanation.matches('O').count(), 23);
+        assert!(explanation.ends_with("22 steps and 14 turns\n"));
+
+        let explanation = Day::explanation(coordinates, Part2)?;
+        assert_eq!(explanation.matches('X').count(), 1);
+        assert_eq!(explanation.lines().nth(1), Some("O##OO#X"));
+        assert!(explanation.ends_with("Byte 6,1 blocks the last path of 24 steps and 15 turns\n"));
+        Ok(())
+    }
+
+    /// Bytes falling on random positions of a memory space of the given size, except for the
+    /// start and the exit.
+    fn random_bytes(size: usize, count: usize, seed: u64) -> Vec<Position> {
+        let mut random = Random::new(seed);
+        (0..count)
+            .map(|_| {
+                let tile = random.below((size * size - 2) as u64) as usize + 1;
+                Position::from((tile % size, tile / size))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn strategies() -> Result<()> {
+        for seed in 0..5 {
+            let coordinates = random_bytes(31, 600, seed);
+            let blocking = [
+                Strategy::Incremental,
+                Strategy::BinarySearch,
+                Strategy::UnionFind,
+            ]
+            .map(|strategy| {
+                let config = Config {
+                    size: 31,
+                    coordinates: 50,
+                    strategy,
+                };
+                Day::first_blocking(&coordinates, &config).map(|byte| (byte.x, byte.y))
+            });
+            assert!(blocking[0].is_ok());
+            assert_eq!(blocking[0].as_ref().ok(), blocking[1].as_ref().ok());
+            assert_eq!(blocking[0].as_ref().ok(), blocking[2].as_ref().ok());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn large_memory_space() -> Result<()> {
+        let coordinates = random_bytes(501, 250_000, 2024);
+        let config = |strategy| Config {
+            size: 501,
+            coordinates: 0,
+            strategy,
+        };
+        assert_eq!(
+            blocking::binary_search(&coordinates, &config(Strategy::BinarySearch))?,
+            blocking::union_find(&coordinates, &config(Strategy::UnionFind))?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn unreachable_exit() {
+        let coordinates = [(1, 0), (0, 1), (2, 2)].map(Position::from).to_vec();
+        for strategy in [
+            Strategy::Incremental,
+            Strategy::BinarySearch,
+            Strategy::UnionFind,
+        ] {
+            let config = |coordinates| Config {
+                size: 3,
+                coordinates,
+                strategy,
+            };
+            assert!(Day::first_blocking(&coordinates, &config(2)).is_err());
+            assert!(Day::first_blocking(&coordinates[..1].to_vec(), &config(0)).is_err());
+            assert!(Day::first_blocking(&coordinates, &config(1)).is_ok());
+        }
+    }
+
+    #[test]
+    fn out_of_range() {
+        assert!(Parser::parse("1,2\n71,0").is_err());
+        let coordinates = [(0, 3), (2, 1)].map(Position::from).to_vec();
+        let config = Config {
+            size: 3,
+            coordinates: 0,
+            strategy: Strategy::default(),
+        };
+        assert!(Day::first_blocking(&coordinates, &config).is_err());
+        assert!(Day::fallen(&coordinates, &config).is_err());
+    }
+
+    #[test]
+    fn count_turns() {
+        let path = [(0, 0), (1, 0), (2, 0), (2, 1), (2, 2), (1, 2)].map(Position::from);
+        assert_eq!(turns(&path), 2);
+    }
 }
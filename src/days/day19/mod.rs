@@ -1,6 +1,9 @@
-use memoize::memoize;
+mod trie;
+
+use anyhow::anyhow;
 
 use crate::*;
+use trie::Trie;
 
 day! {
     Output = usize,
@@ -13,46 +16,38 @@ struct Input {
 }
 
 impl Day {
-    fn part1(parsed: Parsed) -> Result<Output> {
-        Ok(parsed
-            .designs
-            .into_iter()
-            .filter(|design| is_possible(design, parsed.patterns.clone()))
-            .count())
+    /// The number of arrangements of a design with the towels of your input, and at most
+    /// `limit` of the arrangements.
+    pub fn arrangements(design: &str, limit: usize) -> Result<(usize, Vec<String>)> {
+        let input = Parser::parse(include_str!("input.txt"))?;
+        let trie = Trie::new(input.patterns);
+        Ok((
+            trie.arrangements(design)?,
+            trie.enumerate(design, limit)?
+                .into_iter()
+                .map(|arrangement| arrangement.join(", "))
+                .collect(),
+        ))
     }
 
-    fn part2(parsed: Parsed) -> Result<Output> {
-        Ok(parsed
-            .designs
-            .into_iter()
-            .map(|design| arrangements(design, parsed.patterns.clone()))
-            .sum())
+    fn part1(parsed: Parsed) -> Result<Output> {
+        let trie = Trie::new(parsed.patterns);
+        let mut count = 0;
+        for design in parsed.designs {
+            if trie.arrangements(design)? > 0 {
+                count += 1;
+            }
+        }
+        Ok(count)
     }
-}
-
-#[memoize]
-fn is_possible(design: &'static str, patterns: Vec<&'static str>) -> bool {
-    patterns.iter().any(|pattern| {
-        design == *pattern
-            || (design.starts_with(pattern)
-                && is_possible(&design[pattern.len()..], patterns.clone()))
-    })
-}
 
-#[memoize]
-fn arrangements(design: &'static str, patterns: Vec<&'static str>) -> usize {
-    patterns
-        .iter()
-        .map(|pattern| {
-            if design == *pattern {
-                1
-            } else if let Some(tail) = design.strip_prefix(pattern) {
-                arrangements(tail, patterns.clone())
-            } else {
-                0
-            }
+    fn part2(parsed: Parsed) -> Result<Output> {
+        let trie = Trie::new(parsed.patterns);
+        parsed.designs.into_iter().try_fold(0usize, |sum, design| {
+            sum.checked_add(trie.arrangements(design)?)
+                .ok_or_else(|| anyhow!("Too many arrangements to count"))
         })
-        .sum()
+    }
 }
 
 impl Parser {
@@ -76,4 +71,38 @@ mod tests {
     test_example!("example1", Part1, 6);
 
     test_example!("example1", Part2, 16);
+
+    #[test]
+    fn enumerate() -> Result<()> {
+        let input = Parser::parse(include_str!("example1.txt"))?;
+        let trie = Trie::new(input.patterns);
+
+        assert_eq!(trie.arrangements("gbbr")?, 4);
+        assert_eq!(
+            trie.enumerate("gbbr", usize::MAX)?,
+            [
+                vec!["g", "b", "b", "r"],
+                vec!["g", "b", "br"],
+                vec!["gb", "b", "r"],
+                vec!["gb", "br"],
+            ]
+        );
+        assert_eq!(trie.enumerate("rrbgbr", 2)?.len(), 2);
+        assert_eq!(trie.arrangements("ubwu")?, 0);
+        assert!(trie.enumerate("ubwu", 10)?.is_empty());
+        assert_eq!(trie.enumerate("", 10)?, [Vec::<&str>::new()]);
+        Ok(())
+    }
+
+    #[test]
+    fn long_design() -> Result<()> {
+        // The number of ways to split n stripes in parts of 1 and 2 stripes is a Fibonacci number
+        let trie = Trie::new(["w", "ww"]);
+        let design = "w".repeat(90);
+        assert_eq!(trie.arrangements(&design)?, 4_660_046_610_375_530_309);
+        assert_eq!(trie.enumerate(&design, 3)?.len(), 3);
+        assert!(trie.arrangements(&"w".repeat(93)).is_err());
+        assert!(trie.enumerate(&"w".repeat(93), 3).is_err());
+        Ok(())
+    }
 }
@@ -0,0 +1,118 @@
+use anyhow::anyhow;
+
+use crate::Result;
+
+/// The towel patterns as a trie, to find all patterns at the start of a design in one pass.
+pub struct Trie {
+    nodes: Vec<Node>,
+}
+
+#[derive(Default)]
+struct Node {
+    children: Vec<(u8, usize)>,
+    is_pattern: bool,
+}
+
+impl Trie {
+    pub fn new<'a>(patterns: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut trie = Self {
+            nodes: vec![Node::default()],
+        };
+        for pattern in patterns {
+            trie.insert(pattern);
+        }
+        trie
+    }
+
+    fn insert(&mut self, pattern: &str) {
+        let mut node = 0;
+        for stripe in pattern.bytes() {
+            node = match self.child(node, stripe) {
+                Some(child) => child,
+                None => {
+                    self.nodes.push(Node::default());
+                    let child = self.nodes.len() - 1;
+                    self.nodes[node].children.push((stripe, child));
+                    child
+                }
+            };
+        }
+        self.nodes[node].is_pattern = true;
+    }
+
+    fn child(&self, node: usize, stripe: u8) -> Option<usize> {
+        self.nodes[node]
+            .children
+            .iter()
+            .find_map(|&(child_stripe, child)| (child_stripe == stripe).then_some(child))
+    }
+
+    /// The end of every pattern that matches the design at `start`.
+    fn pattern_ends<'a>(
+        &'a self,
+        design: &'a [u8],
+        start: usize,
+    ) -> impl Iterator<Item = usize> + 'a {
+        let mut node = Some(0);
+        (start..design.len())
+            .map_while(move |index| {
+                node = self.child(node?, design[index]);
+                node.map(|node| (index + 1, self.nodes[node].is_pattern))
+            })
+            .filter_map(|(end, is_pattern)| is_pattern.then_some(end))
+    }
+
+    /// The number of arrangements for every suffix of the design, so the first value is the
+    /// number of arrangements of the whole design.
+    fn suffix_arrangements(&self, design: &str) -> Result<Vec<usize>> {
+        let design = design.as_bytes();
+        let mut arrangements = vec![0; design.len() + 1];
+        arrangements[design.len()] = 1;
+        for start in (0..design.len()).rev() {
+            arrangements[start] = self
+                .pattern_ends(design, start)
+                .try_fold(0usize, |sum, end| sum.checked_add(arrangements[end]))
+                .ok_or_else(|| anyhow!("Too many arrangements to count"))?;
+        }
+        Ok(arrangements)
+    }
+
+    pub fn arrangements(&self, design: &str) -> Result<usize> {
+        Ok(self.suffix_arrangements(design)?[0])
+    }
+
+    /// Lists the arrangements of the design, at most `limit` of them.
+    pub fn enumerate<'a>(&self, design: &'a str, limit: usize) -> Result<Vec<Vec<&'a str>>> {
+        let suffixes = self.suffix_arrangements(design)?;
+        let mut arrangements = Vec::new();
+        let mut current = Vec::new();
+        self.enumerate_from(design, 0, &suffixes, limit, &mut current, &mut arrangements);
+        Ok(arrangements)
+    }
+
+    fn enumerate_from<'a>(
+        &self,
+        design: &'a str,
+        start: usize,
+        suffixes: &[usize],
+        limit: usize,
+        current: &mut Vec<&'a str>,
+        arrangements: &mut Vec<Vec<&'a str>>,
+    ) {
+        if start == design.len() {
+            arrangements.push(current.clone());
+            return;
+        }
+        for end in self.pattern_ends(design.as_bytes(), start) {
+            if arrangements.len() >= limit {
+                return;
+            }
+            // Skip patterns after which the rest of the design can't be made
+            if suffixes[end] > 0 {
+                current.push(&design[start..end]);
+                self.enumerate_from(design, end, suffixes, limit, current, arrangements);
+                current.pop();
+            }
+        }
+    }
+}
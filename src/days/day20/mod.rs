@@ -1,5 +1,8 @@
-use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::collections::{BTreeMap, BTreeSet};
 
+use anyhow::bail;
+
+use crate::visualize::{Canvas, Cell, Rgb};
 use crate::*;
 
 day! {
@@ -51,12 +54,16 @@ impl Position {
         }
     }
 
-    fn manhatten_distance(&self, rhs: Position) -> isize {
-        (self.x.abs_diff(rhs.x) + self.y.abs_diff(rhs.y)) as isize
+    fn manhatten_distance(&self, rhs: Position) -> usize {
+        self.x.abs_diff(rhs.x) + self.y.abs_diff(rhs.y)
     }
 }
 
 impl Day {
+    pub fn explain(part: Part) -> Result<String> {
+        Self::explanation(Parser::parse(include_str!("input.txt"))?, part)
+    }
+
     fn part1(input: Parsed) -> Result<Output> {
         Self::count_cheats(&input, 2, if input.is_example { 1 } else { 100 })
     }
@@ -65,49 +72,121 @@ impl Day {
         Self::count_cheats(&input, 20, if input.is_example { 50 } else { 100 })
     }
 
-    fn count_cheats(input: &Parsed, cheat_length: isize, min_picoseconds: isize) -> Result<Output> {
-        let mut position = input.end;
-        let mut steps = 0;
-        let mut visited = BTreeMap::new();
-        let mut cheats = HashSet::new();
+    fn count_cheats(input: &Parsed, cheat_length: usize, min_picoseconds: usize) -> Result<Output> {
+        let path = Self::path(input)?;
+        visualize::record(|| Self::frame(input, &path));
+
+        let mut count = 0;
+        Self::cheats(&path, cheat_length, min_picoseconds, |_| count += 1);
+        Ok(count)
+    }
+
+    /// Number of cheats per number of picoseconds they save, like the breakdown in the puzzle.
+    fn histogram(
+        input: &Parsed,
+        cheat_length: usize,
+        min_picoseconds: usize,
+    ) -> Result<BTreeMap<usize, usize>> {
+        let path = Self::path(input)?;
+        let mut histogram = BTreeMap::new();
+        Self::cheats(&path, cheat_length, min_picoseconds, |saved| {
+            *histogram.entry(saved).or_default() += 1
+        });
+        Ok(histogram)
+    }
 
-        visited.insert(position, 0);
+    /// Calls `cheat` with the picoseconds saved by every cheat that saves at least
+    /// `min_picoseconds`. The index of a tile in the path is its distance from the start, so a
+    /// cheat from tile `i` to tile `j` saves `j - i` minus the length of the cheat.
+    fn cheats(
+        path: &[Position],
+        cheat_length: usize,
+        min_picoseconds: usize,
+        mut cheat: impl FnMut(usize),
+    ) {
+        let min_picoseconds = min_picoseconds.max(1);
+        for (i, from) in path.iter().enumerate() {
+            let mut j = i + min_picoseconds + 1;
+            while j < path.len() {
+                let distance = from.manhatten_distance(path[j]);
+                if distance > cheat_length {
+                    // Every step along the path changes the distance by at most one.
+                    j += distance - cheat_length;
+                    continue;
+                }
+                if j - i >= distance + min_picoseconds {
+                    cheat(j - i - distance);
+                }
+                j += 1;
+            }
+        }
+    }
 
-        while position != input.start {
-            for next in [
+    /// The tiles of the track in order, from the start to the end.
+    fn path(input: &Parsed) -> Result<Vec<Position>> {
+        let mut path = vec![input.start];
+        let mut previous = None;
+        while *path.last().unwrap() != input.end {
+            let position = *path.last().unwrap();
+            let mut next = [
                 position.left(),
                 position.right(),
                 position.up(),
                 position.down(),
-            ] {
-                if !input.track.contains(&next) || visited.contains_key(&next) {
-                    continue;
-                }
-
-                steps += 1;
-                position = next;
-                visited.insert(position, steps);
-
-                for next_y in position.y - cheat_length..=position.y + cheat_length {
-                    for next_x in position.x - cheat_length..=position.x + cheat_length {
-                        let next = Position {
-                            x: next_x,
-                            y: next_y,
-                        };
-                        let manhatten_distance = position.manhatten_distance(next);
-                        if manhatten_distance <= cheat_length {
-                            if let Some(steps_next) = visited.get(&next) {
-                                if steps - manhatten_distance - steps_next >= min_picoseconds {
-                                    cheats.insert((position, next));
-                                }
-                            }
-                        }
-                    }
+            ]
+            .into_iter()
+            .filter(|next| input.track.contains(next) && Some(*next) != previous);
+            match (next.next(), next.next()) {
+                (Some(next), None) => {
+                    previous = Some(position);
+                    path.push(next);
                 }
+                (None, _) => bail!("The track does not reach the end"),
+                (Some(_), Some(_)) => bail!("The track splits at {},{}", position.x, position.y),
             }
         }
+        Ok(path)
+    }
 
-        Ok(cheats.len())
+    fn explanation(input: Parsed, part: Part) -> Result<String> {
+        let (cheat_length, min_picoseconds) = match part {
+            Part1 => (2, 1),
+            Part2 => (20, if input.is_example { 50 } else { 100 }),
+        };
+        Ok(Self::histogram(&input, cheat_length, min_picoseconds)?
+            .into_iter()
+            .map(|(saved, count)| match count {
+                1 => format!("There is one cheat that saves {saved} picoseconds.\n"),
+                _ => format!("There are {count} cheats that save {saved} picoseconds.\n"),
+            })
+            .collect())
+    }
+
+    fn frame(input: &Parsed, path: &[Position]) -> Canvas {
+        let width = input
+            .track
+            .iter()
+            .map(|position| position.x)
+            .max()
+            .unwrap_or(0)
+            + 2;
+        let height = input
+            .track
+            .iter()
+            .map(|position| position.y)
+            .max()
+            .unwrap_or(0)
+            + 2;
+        let mut canvas = Canvas::new(width as usize, height as usize, Cell::new('#', Rgb::GRAY));
+        for (distance, position) in path.iter().enumerate() {
+            let color = Rgb::RED.lerp(Rgb::GREEN, distance as f32 / path.len() as f32);
+            canvas.set(
+                position.x as usize,
+                position.y as usize,
+                Cell::new('.', color),
+            );
+        }
+        canvas
     }
 }
 
@@ -160,4 +239,70 @@ mod tests {
     test_example!("example1", Part1, 44);
 
     test_example!("example1", Part2, 285);
+
+    #[test]
+    fn histogram_part1() -> Result<()> {
+        let input = Parser::parse(include_str!("example1.txt"))?;
+        assert_eq!(
+            Day::histogram(&input, 2, 1)?,
+            BTreeMap::from([
+                (2, 14),
+                (4, 14),
+                (6, 2),
+                (8, 4),
+                (10, 2),
+                (12, 3),
+                (20, 1),
+                (36, 1),
+                (38, 1),
+                (40, 1),
+                (64, 1),
+            ])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn histogram_part2() -> Result<()> {
+        let input = Parser::parse(include_str!("example1.txt"))?;
+        assert_eq!(
+            Day::histogram(&input, 20, 50)?,
+            BTreeMap::from([
+                (50, 32),
+                (52, 31),
+                (54, 29),
+                (56, 39),
+                (58, 25),
+                (60, 23),
+                (62, 20),
+                (64, 19),
+                (66, 12),
+                (68, 14),
+                (70, 12),
+                (72, 22),
+                (74, 4),
+                (76, 3),
+            ])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn explanation() -> Result<()> {
+        let explanation = Day::explanation(Parser::parse(include_str!("example1.txt"))?, Part1)?;
+        assert!(explanation.starts_with(
+            "There are 14 cheats that save 2 picoseconds.\n\
+             There are 14 cheats that save 4 picoseconds.\n"
+        ));
+        assert!(explanation.ends_with("There is one cheat that saves 64 picoseconds.\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn broken_track() {
+        let parse = |track| Parser::parse(track).and_then(|input| Day::path(&input));
+        assert!(parse("#####\n#S#E#\n#####").is_err());
+        assert!(parse("#####\n#...#\n#S.E#\n#####").is_err());
+        assert_eq!(parse("#####\n#S.E#\n#####").unwrap().len(), 3);
+    }
 }
@@ -1,5 +1,9 @@
-use std::collections::{BTreeMap, BTreeSet};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    iter,
+};
 
+use anyhow::{anyhow, bail};
 use memoize::memoize;
 use pathfinding::prelude::astar_bag;
 
@@ -27,56 +31,178 @@ struct Position {
     y: isize,
 }
 
+const NUMERIC: &str = "789\n456\n123\n 0A";
+
+const DIRECTIONAL: &str = " ^A\n<v>";
+
+/// A chain of keypads: the first keypad is the one the code is typed on, every next keypad
+/// controls the robot arm on the keypad before it, and the last keypad is used by you.
+struct KeypadChain {
+    keypads: Vec<Keypad>,
+    costs: Vec<BTreeMap<(char, char), usize>>,
+}
+
 impl Keypad {
-    fn new_numeric() -> Self {
-        Self::new([
-            [Some('7'), Some('8'), Some('9')],
-            [Some('4'), Some('5'), Some('6')],
-            [Some('1'), Some('2'), Some('3')],
-            [None, Some('0'), Some('A')],
-        ])
+    /// Reads a layout with one row of keys per line, where a space is a gap.
+    fn new(layout: &str) -> Result<Self> {
+        let mut keys = BTreeMap::new();
+        for (y, row) in layout.lines().enumerate() {
+            for (x, key) in row.chars().enumerate().filter(|(_, key)| *key != ' ') {
+                let position = Position {
+                    x: x as isize,
+                    y: y as isize,
+                };
+                if keys.insert(key, position).is_some() {
+                    bail!("Key {key} appears more than once on the keypad");
+                }
+            }
+        }
+        if !keys.contains_key(&'A') {
+            bail!("Keypad has no A key");
+        }
+        let keypad = Self { keys };
+        if let Some(key) = keypad.unreachable_key() {
+            bail!("Key {key} can not be reached from the A key");
+        }
+        Ok(keypad)
     }
 
-    fn new_directional() -> Self {
-        Self::new([
-            [None, Some('^'), Some('A')],
-            [Some('<'), Some('v'), Some('>')],
-        ])
+    /// A key that the robot arm can not move to from the A key without pointing at a gap.
+    fn unreachable_key(&self) -> Option<char> {
+        let positions = self.keys.values().copied().collect::<BTreeSet<_>>();
+        let mut reached = BTreeSet::from([self.keys[&'A']]);
+        let mut stack = vec![self.keys[&'A']];
+        while let Some(position) = stack.pop() {
+            for next in [
+                position.left(),
+                position.right(),
+                position.up(),
+                position.down(),
+            ] {
+                if positions.contains(&next) && reached.insert(next) {
+                    stack.push(next);
+                }
+            }
+        }
+        self.keys
+            .iter()
+            .find(|(_, position)| !reached.contains(position))
+            .map(|(key, _)| *key)
     }
 
-    fn new(keys: impl IntoIterator<Item = [Option<char>; 3]>) -> Self {
-        Self {
-            keys: keys
-                .into_iter()
-                .enumerate()
-                .flat_map(|(y, row)| {
-                    row.into_iter()
-                        .enumerate()
-                        .filter(|(_, c)| c.is_some())
-                        .map(move |(x, c)| {
-                            (
-                                c.unwrap(),
-                                Position {
-                                    x: x as isize,
-                                    y: y as isize,
-                                },
-                            )
-                        })
-                })
-                .collect(),
-        }
+    fn is_directional(&self) -> bool {
+        ['^', 'v', '<', '>', 'A']
+            .iter()
+            .all(|key| self.keys.contains_key(key))
     }
 }
 
 impl From<KeypadType> for Keypad {
     fn from(keypad_type: KeypadType) -> Self {
         match keypad_type {
-            KeypadType::Numeric => Keypad::new_numeric(),
-            KeypadType::Directional => Keypad::new_directional(),
+            KeypadType::Numeric => Keypad::new(NUMERIC).unwrap(),
+            KeypadType::Directional => Keypad::new(DIRECTIONAL).unwrap(),
         }
     }
 }
 
+impl KeypadChain {
+    fn new(keypads: Vec<Keypad>) -> Result<Self> {
+        if keypads.is_empty() {
+            bail!("Keypad chain is empty");
+        }
+        if !keypads[1..].iter().all(Keypad::is_directional) {
+            bail!("Keypads controlling a robot need the keys ^, v, <, > and A");
+        }
+
+        let mut costs = vec![BTreeMap::new(); keypads.len()];
+        let last = keypads.len() - 1;
+        for start in keypads[last].keys.keys().copied() {
+            for end in keypads[last].keys.keys().copied() {
+                costs[last].insert((start, end), 1);
+            }
+        }
+        for layer in (0..last).rev() {
+            for start in keypads[layer].keys.keys().copied() {
+                for end in keypads[layer].keys.keys().copied() {
+                    let cost = routes(keypads[layer].clone(), start, end)
+                        .iter()
+                        .filter_map(|route| Self::route_cost(&costs[layer + 1], route))
+                        .min()
+                        .ok_or_else(Self::too_many_presses)?;
+                    costs[layer].insert((start, end), cost);
+                }
+            }
+        }
+
+        Ok(Self { keypads, costs })
+    }
+
+    fn with_robots(robots: usize) -> Result<Self> {
+        let mut keypads = vec![Keypad::from(KeypadType::Numeric)];
+        keypads.extend(iter::repeat_n(
+            Keypad::from(KeypadType::Directional),
+            robots,
+        ));
+        keypads.push(Keypad::from(KeypadType::Directional));
+        Self::new(keypads)
+    }
+
+    fn too_many_presses() -> anyhow::Error {
+        anyhow!("Too many button presses to count")
+    }
+
+    /// The number of buttons you have to press to move along `route`, or `None` if there are
+    /// too many to count.
+    fn route_cost(costs: &BTreeMap<(char, char), usize>, route: &[char]) -> Option<usize> {
+        iter::once(&'A')
+            .chain(route)
+            .zip(route)
+            .try_fold(0usize, |cost, (start, end)| {
+                cost.checked_add(costs[&(*start, *end)])
+            })
+    }
+
+    /// The number of buttons you have to press to type `code` on the first keypad.
+    fn presses(&self, code: &[char]) -> Result<usize> {
+        iter::once(&'A')
+            .chain(code)
+            .zip(code)
+            .try_fold(0usize, |presses, (start, end)| {
+                let cost = self.costs[0]
+                    .get(&(*start, *end))
+                    .copied()
+                    .ok_or_else(|| anyhow!("Key {end} is not on the keypad"))?;
+                presses.checked_add(cost).ok_or_else(Self::too_many_presses)
+            })
+    }
+
+    /// The optimal button sequences on every keypad of the chain, starting with `code` and
+    /// ending with the buttons you press.
+    fn sequences(&self, code: &[char]) -> Result<Vec<Vec<char>>> {
+        self.presses(code)?;
+
+        let mut sequences = vec![code.to_vec()];
+        for layer in 0..self.keypads.len() - 1 {
+            let sequence = sequences.last().unwrap();
+            let next = iter::once(&'A')
+                .chain(sequence)
+                .zip(sequence)
+                .flat_map(|(start, end)| {
+                    routes(self.keypads[layer].clone(), *start, *end)
+                        .into_iter()
+                        .min_by_key(|route| {
+                            Self::route_cost(&self.costs[layer + 1], route).unwrap_or(usize::MAX)
+                        })
+                        .unwrap()
+                })
+                .collect();
+            sequences.push(next);
+        }
+        Ok(sequences)
+    }
+}
+
 impl Position {
     fn left(&self) -> Position {
         Position {
@@ -125,53 +251,49 @@ impl Position {
 }
 
 impl Day {
-    fn part1(codes: Parsed) -> Result<Output> {
-        Self::complexities(codes, 4)
-    }
+    pub fn sequences(code: &str, robots: usize) -> Result<Vec<String>> {
+        let chain = KeypadChain::with_robots(robots)?;
+        let code = code.chars().collect::<Vec<_>>();
+        let presses = chain.presses(&code)?;
+        if presses > 10_000 {
+            bail!("Sequence of {presses} button presses is too long to display");
+        }
 
-    fn part2(codes: Parsed) -> Result<Output> {
-        Self::complexities(codes, 27)
+        Ok(chain
+            .sequences(&code)?
+            .into_iter()
+            .map(|sequence| sequence.into_iter().collect())
+            .collect())
     }
 
-    fn complexities(codes: Parsed, depth: usize) -> Result<Output> {
-        Ok(codes
-            .into_iter()
-            .map(|code| {
-                let code_num = code[0].to_digit(10).unwrap() as usize * 100
-                    + code[1].to_digit(10).unwrap() as usize * 10
-                    + code[2].to_digit(10).unwrap() as usize;
-                let steps = code_steps(KeypadType::Numeric, 'A', code.clone(), depth);
-                code_num * steps
-            })
-            .sum())
+    fn part1(codes: Parsed) -> Result<Output> {
+        Self::complexities(codes, 2)
     }
-}
 
-#[memoize]
-fn code_steps(keypad_type: KeypadType, prev: char, code: Vec<char>, depth: usize) -> usize {
-    if depth == 0 {
-        1
-    } else if code.is_empty() {
-        0
-    } else {
-        let mut code = code.clone();
-        let next = code.remove(0);
-
-        let inner_steps = routes(keypad_type, prev, next)
-            .into_iter()
-            .map(|inner_code| {
-                code_steps(KeypadType::Directional, 'A', inner_code.clone(), depth - 1)
-            })
-            .min()
-            .unwrap();
+    fn part2(codes: Parsed) -> Result<Output> {
+        Self::complexities(codes, 25)
+    }
 
-        inner_steps + code_steps(keypad_type, next, code, depth)
+    fn complexities(codes: Parsed, robots: usize) -> Result<Output> {
+        let chain = KeypadChain::with_robots(robots)?;
+        codes.into_iter().try_fold(0usize, |sum, code| {
+            let code_num = code
+                .iter()
+                .filter_map(|c| c.to_digit(10))
+                .try_fold(0usize, |acc, digit| {
+                    acc.checked_mul(10)?.checked_add(digit as usize)
+                })
+                .ok_or_else(|| anyhow!("Code {} is too large", String::from_iter(&code)))?;
+            code_num
+                .checked_mul(chain.presses(&code)?)
+                .and_then(|complexity| sum.checked_add(complexity))
+                .ok_or_else(KeypadChain::too_many_presses)
+        })
     }
 }
 
 #[memoize]
-fn routes(keypad_type: KeypadType, start: char, end: char) -> Vec<Vec<char>> {
-    let keypad = Keypad::from(keypad_type);
+fn routes(keypad: Keypad, start: char, end: char) -> Vec<Vec<char>> {
     let valid_positions = keypad.keys.values().collect::<BTreeSet<_>>();
 
     astar_bag(
@@ -213,4 +335,102 @@ mod tests {
     run!(Part2);
 
     test_example!("example1", Part1, 126384);
+
+    test_example!("example1", Part2, 154115708116294usize);
+
+    fn replay(chain: &KeypadChain, presses: &[char]) -> Result<Vec<char>> {
+        let mut arms = vec!['A'; chain.keypads.len()];
+        let mut output = Vec::new();
+        for &press in presses {
+            let mut key = press;
+            let mut layer = chain.keypads.len() - 1;
+            loop {
+                if layer == 0 {
+                    output.push(key);
+                    break;
+                }
+                layer -= 1;
+                if key == 'A' {
+                    key = arms[layer];
+                    continue;
+                }
+                let keypad = &chain.keypads[layer];
+                let position = keypad.keys[&arms[layer]];
+                let next = match key {
+                    '<' => position.left(),
+                    '>' => position.right(),
+                    '^' => position.up(),
+                    'v' => position.down(),
+                    _ => bail!("Unknown direction {key}"),
+                };
+                arms[layer] = keypad
+                    .keys
+                    .iter()
+                    .find(|(_, position)| **position == next)
+                    .map(|(key, _)| *key)
+                    .ok_or_else(|| anyhow!("Robot arm points at a gap"))?;
+                break;
+            }
+        }
+        Ok(output)
+    }
+
+    #[test]
+    fn sequences() -> Result<()> {
+        let chain = KeypadChain::with_robots(2)?;
+        for (code, presses) in [
+            ("029A", 68),
+            ("980A", 60),
+            ("179A", 68),
+            ("456A", 64),
+            ("379A", 64),
+        ] {
+            let code = code.chars().collect::<Vec<_>>();
+            let sequences = chain.sequences(&code)?;
+            assert_eq!(sequences.len(), 4);
+            assert_eq!(sequences[3].len(), presses);
+            assert_eq!(chain.presses(&code)?, presses);
+            assert_eq!(replay(&chain, &sequences[3])?, code);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn display_sequences() -> Result<()> {
+        let sequences = Day::sequences("029A", 0)?;
+        assert_eq!(sequences[0], "029A");
+        assert_eq!(sequences[1].len(), 12);
+        assert!(Day::sequences("029A", 20).is_err());
+        assert!(Day::sequences("02B", 1).is_err());
+        assert!(Day::sequences("029A", 60).is_err());
+        assert!(Day::complexities(vec!["029A".chars().collect()], 60).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn custom_layouts() -> Result<()> {
+        let hex = Keypad::new("ABC\n789\n456\n123\n 0F")?;
+        let wide = Keypad::new("<^v>A")?;
+        let chain = KeypadChain::new(vec![
+            hex,
+            Keypad::from(KeypadType::Directional),
+            wide,
+            Keypad::from(KeypadType::Directional),
+        ])?;
+        let code = "1F0C".chars().collect::<Vec<_>>();
+        let sequences = chain.sequences(&code)?;
+        assert_eq!(sequences[3].len(), chain.presses(&code)?);
+        assert_eq!(replay(&chain, &sequences[3])?, code);
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_layouts() {
+        assert!(Keypad::new("123\n456").is_err());
+        assert!(Keypad::new("1A1").is_err());
+        assert!(Keypad::new("1 A").is_err());
+        assert!(Keypad::new("A2\n  \n13").is_err());
+        assert!(KeypadChain::new(vec![Keypad::from(KeypadType::Numeric); 2]).is_err());
+        assert!(KeypadChain::new(Vec::new()).is_err());
+    }
 }
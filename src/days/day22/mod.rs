@@ -1,92 +1,195 @@
-use std::{
-    array,
-    collections::{BTreeMap, BTreeSet},
-    simd::prelude::*,
-};
+use std::{collections::BTreeSet, iter::once};
+
+use anyhow::{anyhow, bail};
 
 use crate::*;
 
 day! {
-    Output = u128,
-    Parsed = [u32; 6_400],
+    Output = u64,
+    Parsed = Vec<u32>,
     bench_sample_size: 10,
 }
 
-impl Day {
-    fn part1(secrets: Parsed) -> Result<Output> {
-        let prune = u32x64::splat(16_777_216);
-        Ok(secrets
-            .chunks(64)
-            .map(|chunk| {
-                let mut secret = u32x64::from_slice(chunk);
-                for _ in 0..2000 {
-                    secret = (secret ^ (secret << 6)) % prune;
-                    secret = (secret ^ (secret >> 5)) % prune;
-                    secret = (secret ^ (secret << 11)) % prune;
-                }
-                secret.reduce_sum() as u128
+const ITERATIONS: usize = 2000;
+
+/// Four consecutive price changes, each between -9 and 9.
+type Sequence = [i8; 4];
+
+/// Number of different sequences.
+const SEQUENCES: usize = 19 * 19 * 19 * 19;
+
+/// The secret numbers of a buyer after the initial one.
+struct SecretGenerator {
+    secret: u32,
+}
+
+impl SecretGenerator {
+    fn new(secret: u32) -> Self {
+        Self { secret }
+    }
+}
+
+impl Iterator for SecretGenerator {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        const PRUNE: u32 = 16_777_216 - 1;
+        self.secret = (self.secret ^ (self.secret << 6)) & PRUNE;
+        self.secret = (self.secret ^ (self.secret >> 5)) & PRUNE;
+        self.secret = (self.secret ^ (self.secret << 11)) & PRUNE;
+        Some(self.secret)
+    }
+}
+
+struct Market {
+    buyers: Vec<u32>,
+    iterations: usize,
+}
+
+/// The bananas bought with every sequence, indexed by the encoded sequence.
+struct PriceTable {
+    bananas: Vec<u32>,
+}
+
+impl Market {
+    fn new(buyers: Vec<u32>, iterations: usize) -> Self {
+        Self { buyers, iterations }
+    }
+
+    fn sum_of_secrets(&self) -> u64 {
+        self.buyers
+            .iter()
+            .map(|&buyer| {
+                SecretGenerator::new(buyer)
+                    .take(self.iterations)
+                    .last()
+                    .unwrap_or(buyer) as u64
             })
-            .sum())
-    }
-
-    fn part2(secrets: Parsed) -> Result<Output> {
-        let prune = u32x64::splat(16_777_216);
-        let ten = u32x64::splat(10);
-        let hundred_thousand = u32x64::splat(100_000);
-        let mut last_5_digits = u32x64::default();
-        let mut sequences = BTreeMap::new();
-        for chunk in secrets.chunks(64) {
-            let mut secret = u32x64::from_slice(chunk);
-            let mut sequences64 = vec![BTreeSet::new(); 64];
-            for i in 0..2000 {
-                secret = (secret ^ (secret << 6)) % prune;
-                secret = (secret ^ (secret >> 5)) % prune;
-                secret = (secret ^ (secret << 11)) % prune;
-                let last = secret % ten;
-                last_5_digits = (last_5_digits * ten + last) % hundred_thousand;
-                if i >= 4 {
-                    for j in 0..64 {
-                        if last[j] > 0 {
-                            let sequence = Self::last_5_digits_to_sequence(last_5_digits[j]);
-                            if !sequences64[j].contains(&sequence) {
-                                sequences64[j].insert(sequence);
-                                *sequences.entry(sequence).or_default() += last[j] as u128;
-                            }
-                        }
-                    }
+            .sum()
+    }
+
+    /// Sells to the selected buyers. A monkey sells to a buyer the first time the sequence
+    /// appears, so every buyer remembers the last buyer that saw each sequence. A buyer that
+    /// is selected more than once still only buys once.
+    fn price_table(&self, buyers: &[usize]) -> Result<PriceTable> {
+        let mut table = PriceTable {
+            bananas: vec![0; SEQUENCES],
+        };
+        let mut seen_by = vec![usize::MAX; SEQUENCES];
+        for index in BTreeSet::from_iter(buyers.iter().copied()) {
+            let &buyer = self
+                .buyers
+                .get(index)
+                .ok_or_else(|| anyhow!("There is no buyer {index}"))?;
+            let prices = once(buyer)
+                .chain(SecretGenerator::new(buyer).take(self.iterations))
+                .map(|secret| (secret % 10) as i8);
+            let mut sequence = 0;
+            let mut previous = None;
+            for (i, price) in prices.enumerate() {
+                if let Some(previous) = previous {
+                    sequence = (sequence * 19 + (price - previous + 9) as usize) % SEQUENCES;
+                }
+                previous = Some(price);
+                if i >= 4 && seen_by[sequence] != index {
+                    seen_by[sequence] = index;
+                    table.bananas[sequence] += price as u32;
                 }
             }
         }
+        Ok(table)
+    }
 
-        Ok(sequences
-            .into_iter()
-            .max_by_key(|(_, bananas)| *bananas)
-            .unwrap()
-            .1)
-    }
-
-    fn last_5_digits_to_sequence(last_5_digits: u32) -> [i8; 4] {
-        let digits = [
-            (last_5_digits % 10) as i8,
-            ((last_5_digits / 10) % 10) as i8,
-            ((last_5_digits / 100) % 10) as i8,
-            ((last_5_digits / 1_000) % 10) as i8,
-            ((last_5_digits / 10_000) % 10) as i8,
-        ];
-
-        [
-            digits[3] - digits[4],
-            digits[2] - digits[3],
-            digits[1] - digits[2],
-            digits[0] - digits[1],
-        ]
+    fn all_buyers(&self) -> Vec<usize> {
+        (0..self.buyers.len()).collect()
+    }
+}
+
+impl PriceTable {
+    fn encode(sequence: Sequence) -> Option<usize> {
+        sequence.into_iter().try_fold(0, |index, change| {
+            (-9..=9)
+                .contains(&change)
+                .then(|| index * 19 + (change + 9) as usize)
+        })
+    }
+
+    fn decode(mut index: usize) -> Sequence {
+        let mut sequence = [0; 4];
+        for change in sequence.iter_mut().rev() {
+            *change = (index % 19) as i8 - 9;
+            index /= 19;
+        }
+        sequence
+    }
+
+    fn bananas(&self, sequence: Sequence) -> Option<u32> {
+        Some(self.bananas[Self::encode(sequence)?])
+    }
+
+    /// The sequence that buys the most bananas, the first one if there are several.
+    fn best(&self) -> (Sequence, u32) {
+        let (index, bananas) =
+            self.bananas
+                .iter()
+                .enumerate()
+                .fold((0, 0), |best, (index, &bananas)| {
+                    if bananas > best.1 {
+                        (index, bananas)
+                    } else {
+                        best
+                    }
+                });
+        (Self::decode(index), bananas)
+    }
+}
+
+impl Day {
+    /// The best sequence for some buyers of your input, or all buyers if none are given, and
+    /// the bananas bought with `sequence`.
+    pub fn bananas(
+        sequence: Option<Sequence>,
+        buyers: &[usize],
+        iterations: usize,
+    ) -> Result<String> {
+        let market = Market::new(Parser::parse(include_str!("input.txt"))?, iterations);
+        Self::query(&market, sequence, buyers)
+    }
+
+    fn part1(buyers: Parsed) -> Result<Output> {
+        Ok(Market::new(buyers, ITERATIONS).sum_of_secrets())
+    }
+
+    fn part2(buyers: Parsed) -> Result<Output> {
+        let market = Market::new(buyers, ITERATIONS);
+        Ok(market.price_table(&market.all_buyers())?.best().1 as Output)
+    }
+
+    fn query(market: &Market, sequence: Option<Sequence>, buyers: &[usize]) -> Result<String> {
+        let table = if buyers.is_empty() {
+            market.price_table(&market.all_buyers())?
+        } else {
+            market.price_table(buyers)?
+        };
+        let format = |sequence: Sequence| sequence.map(|change| change.to_string()).join(",");
+        let (best, bananas) = table.best();
+        let mut answer = format!("Best sequence {}: {bananas} bananas\n", format(best));
+        if let Some(sequence) = sequence {
+            let Some(bananas) = table.bananas(sequence) else {
+                bail!("Price changes must be between -9 and 9");
+            };
+            answer += &format!("Sequence {}: {bananas} bananas\n", format(sequence));
+        }
+        Ok(answer)
     }
 }
 
 impl Parser {
     fn parse(input: &'static str) -> Result<Parsed> {
-        let mut iter = input.lines().map(|line| line.parse().unwrap());
-        Ok(array::from_fn(|_| iter.next().unwrap_or_default()))
+        Ok(input
+            .lines()
+            .map(|line| line.parse())
+            .collect::<Result<_, _>>()?)
     }
 }
 
@@ -101,4 +204,59 @@ mod tests {
     test_example!("example1", Part1, 37327623);
 
     test_example!("example2", Part2, 23);
+
+    #[test]
+    fn secrets() {
+        assert_eq!(
+            SecretGenerator::new(123).take(10).collect::<Vec<_>>(),
+            [
+                15887950, 16495136, 527345, 704524, 1553684, 12683156, 11100544, 12249484, 7753432,
+                5908254
+            ]
+        );
+        assert_eq!(Market::new(vec![1, 10], 0).sum_of_secrets(), 11);
+        assert_eq!(Market::new(vec![1], 2000).sum_of_secrets(), 8685429);
+    }
+
+    #[test]
+    fn price_table() -> Result<()> {
+        let market = Market::new(vec![123], 9);
+        assert_eq!(market.price_table(&[0])?.best(), ([-1, -1, 0, 2], 6));
+
+        let market = Market::new(vec![1, 2, 3, 2024], ITERATIONS);
+        let table = market.price_table(&market.all_buyers())?;
+        assert_eq!(table.bananas([-2, 1, -1, 3]), Some(23));
+        assert_eq!(table.bananas([-10, 0, 0, 0]), None);
+        let bananas = [0, 1, 2, 3]
+            .map(|buyer| Ok(market.price_table(&[buyer])?.bananas([-2, 1, -1, 3])))
+            .into_iter()
+            .collect::<Result<Vec<_>>>()?;
+        assert_eq!(bananas, [Some(7), Some(7), Some(0), Some(9)]);
+        assert!(market.price_table(&[4]).is_err());
+        assert_eq!(
+            market.price_table(&[0, 1, 0])?.bananas([-2, 1, -1, 3]),
+            Some(14)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn encoding() {
+        for sequence in [[-9, -9, -9, -9], [9, 9, 9, 9], [-2, 1, -1, 3]] {
+            let index = PriceTable::encode(sequence).unwrap();
+            assert!(index < SEQUENCES);
+            assert_eq!(PriceTable::decode(index), sequence);
+        }
+    }
+
+    #[test]
+    fn query() -> Result<()> {
+        let market = Market::new(vec![1, 2, 3, 2024], ITERATIONS);
+        assert_eq!(
+            Day::query(&market, Some([-2, 1, -1, 3]), &[])?,
+            "Best sequence -2,1,-1,3: 23 bananas\nSequence -2,1,-1,3: 23 bananas\n"
+        );
+        assert!(Day::query(&market, Some([0, 0, 0, 10]), &[0]).is_err());
+        Ok(())
+    }
 }
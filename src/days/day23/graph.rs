@@ -0,0 +1,108 @@
+use std::collections::BTreeSet;
+
+use super::{NodeName, ParsedInput};
+
+impl ParsedInput {
+    fn neighbours(&self, node: NodeName) -> &BTreeSet<NodeName> {
+        &self.all[node]
+    }
+
+    /// Finds a largest set of nodes that are all connected to each other, using
+    /// Bron–Kerbosch with pivoting.
+    pub fn maximum_clique(&self) -> BTreeSet<NodeName> {
+        let mut maximum = Vec::new();
+        self.bron_kerbosch(
+            &mut Vec::new(),
+            self.nodes.clone(),
+            BTreeSet::new(),
+            &mut maximum,
+        );
+        maximum.into_iter().collect()
+    }
+
+    fn bron_kerbosch(
+        &self,
+        clique: &mut Vec<NodeName>,
+        mut candidates: BTreeSet<NodeName>,
+        mut excluded: BTreeSet<NodeName>,
+        maximum: &mut Vec<NodeName>,
+    ) {
+        if candidates.is_empty() && excluded.is_empty() {
+            if clique.len() > maximum.len() {
+                maximum.clone_from(clique);
+            }
+            return;
+        }
+        if clique.len() + candidates.len() <= maximum.len() {
+            return;
+        }
+
+        let pivot = candidates
+            .union(&excluded)
+            .copied()
+            .max_by_key(|&node| self.neighbours(node).intersection(&candidates).count())
+            .unwrap();
+        let pivot_neighbours = self.neighbours(pivot);
+
+        for node in candidates
+            .difference(pivot_neighbours)
+            .copied()
+            .collect::<Vec<_>>()
+        {
+            let neighbours = self.neighbours(node);
+            clique.push(node);
+            self.bron_kerbosch(
+                clique,
+                candidates.intersection(neighbours).copied().collect(),
+                excluded.intersection(neighbours).copied().collect(),
+                maximum,
+            );
+            clique.pop();
+            candidates.remove(node);
+            excluded.insert(node);
+        }
+    }
+
+    /// Enumerates all sets of `k` nodes that are all connected to each other and contain at
+    /// least one node matching `predicate`.
+    pub fn cliques(
+        &self,
+        k: usize,
+        predicate: impl Fn(NodeName) -> bool,
+    ) -> Vec<BTreeSet<NodeName>> {
+        let mut cliques = Vec::new();
+        self.extend_clique(k, &mut Vec::new(), &self.nodes, &predicate, &mut cliques);
+        cliques
+    }
+
+    fn extend_clique(
+        &self,
+        k: usize,
+        clique: &mut Vec<NodeName>,
+        candidates: &BTreeSet<NodeName>,
+        predicate: &impl Fn(NodeName) -> bool,
+        cliques: &mut Vec<BTreeSet<NodeName>>,
+    ) {
+        if clique.len() == k {
+            if clique.iter().copied().any(predicate) {
+                cliques.push(clique.iter().copied().collect());
+            }
+            return;
+        }
+        if clique.len() + candidates.len() < k {
+            return;
+        }
+
+        for &node in candidates {
+            let next = candidates
+                .range(node..)
+                .skip(1)
+                .filter(|next| self.neighbours(node).contains(*next))
+                .copied()
+                .collect();
+            clique.push(node);
+            self.extend_clique(k, clique, &next, predicate, cliques);
+            clique.pop();
+        }
+    }
+}
@@ -1,3 +1,5 @@
+mod graph;
+
 use std::collections::{BTreeMap, BTreeSet};
 
 use crate::*;
@@ -12,58 +14,23 @@ type NodeName = &'static str;
 #[derive(Default)]
 struct ParsedInput {
     nodes: BTreeSet<NodeName>,
-    forward: BTreeMap<NodeName, BTreeSet<NodeName>>,
     all: BTreeMap<NodeName, BTreeSet<NodeName>>,
 }
 
 impl Day {
     fn part1(parsed: Parsed) -> Result<Output> {
-        let mut interconnected = BTreeSet::new();
-        for node1 in parsed
-            .nodes
-            .iter()
-            .copied()
-            .filter(|node_name| node_name.starts_with("t"))
-        {
-            let mut set = BTreeSet::new();
-            set.insert(node1);
-
-            if let Some(reachable) = parsed.all.get(node1) {
-                for node2 in reachable {
-                    if let Some(reachable_node2) = parsed.forward.get(node2) {
-                        for node3 in reachable.iter() {
-                            if reachable_node2.contains(node3) {
-                                let mut set2 = set.clone();
-                                set2.insert(node2);
-                                set2.insert(node3);
-                                interconnected.insert(set2);
-                            }
-                        }
-                    }
-                }
-            }
-        }
-        Ok(interconnected.len().to_string())
+        Ok(parsed
+            .cliques(3, |node| node.starts_with("t"))
+            .len()
+            .to_string())
     }
 
     fn part2(parsed: Parsed) -> Result<Output> {
-        let path = parsed
-            .nodes
-            .iter()
-            .copied()
-            .map(|node| {
-                let mut connected = BTreeSet::new();
-                connected.insert(node);
-                for (next, reachable) in &parsed.all {
-                    if connected.intersection(reachable).count() == connected.len() {
-                        connected.insert(*next);
-                    }
-                }
-                connected
-            })
-            .max_by_key(|path| path.len())
-            .unwrap();
-        Ok(path.into_iter().collect::<Vec<_>>().join(","))
+        Ok(parsed
+            .maximum_clique()
+            .into_iter()
+            .collect::<Vec<_>>()
+            .join(","))
     }
 }
 
@@ -74,7 +41,6 @@ impl Parser {
             let (left, right) = line.split_once("-").unwrap();
             parsed.nodes.insert(left);
             parsed.nodes.insert(right);
-            parsed.forward.entry(left).or_default().insert(right);
             parsed.all.entry(right).or_default().insert(left);
             parsed.all.entry(left).or_default().insert(right);
         }
@@ -93,4 +59,61 @@ mod tests {
     test_example!("example1", Part1, 7);
 
     test_example!("example1", Part2, "co,de,ka,ta");
+
+    fn graph(nodes: usize, planted: &[usize], density: u64) -> Result<ParsedInput> {
+        let mut random = Random::new(0x2024);
+        let mut input = Vec::new();
+        for a in 0..nodes {
+            for b in a + 1..nodes {
+                if (planted.contains(&a) && planted.contains(&b)) || random.below(100) < density {
+                    input.push(format!("n{a:02}-n{b:02}"));
+                }
+            }
+        }
+        Parser::parse(input.join("\n").leak())
+    }
+
+    #[test]
+    fn planted_maximum_clique() -> Result<()> {
+        let planted = [3, 8, 14, 15, 21, 27, 33, 38];
+        let parsed = graph(40, &planted, 15)?;
+        assert_eq!(
+            parsed.maximum_clique(),
+            planted
+                .iter()
+                .map(|node| format!("n{node:02}").leak() as NodeName)
+                .collect()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn complete_graph_cliques() -> Result<()> {
+        let parsed = graph(6, &[0, 1, 2, 3, 4, 5], 0)?;
+        assert_eq!(parsed.maximum_clique().len(), 6);
+        assert_eq!(parsed.cliques(3, |_| true).len(), 20);
+        assert_eq!(parsed.cliques(4, |_| true).len(), 15);
+        assert_eq!(parsed.cliques(3, |node| node == "n00").len(), 10);
+        assert_eq!(parsed.cliques(7, |_| true).len(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn greedy_trap() -> Result<()> {
+        let parsed = Parser::parse("p-q\np-r\np-s\nq-r\nq-s\nr-s\na-p\nb-q\nc-r\nd-s")?;
+        assert_eq!(
+            parsed.maximum_clique(),
+            BTreeSet::from(["p", "q", "r", "s"])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn cycle_cliques() -> Result<()> {
+        let parsed = Parser::parse("a-b\nb-c\nc-d\nd-e\ne-a")?;
+        assert_eq!(parsed.maximum_clique().len(), 2);
+        assert_eq!(parsed.cliques(3, |_| true).len(), 0);
+        assert_eq!(parsed.cliques(2, |node| node == "a").len(), 2);
+        Ok(())
+    }
 }
@@ -0,0 +1,190 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fmt::Write,
+};
+
+use anyhow::{anyhow, bail};
+
+use super::{Operation, Wire};
+use crate::Result;
+
+#[derive(Debug, Clone, Copy)]
+struct Gate {
+    input1: usize,
+    operation: Operation,
+    input2: usize,
+    output: usize,
+}
+
+/// The wire network with its gates sorted topologically, so every gate can be evaluated
+/// after the gates that drive its inputs.
+pub struct Circuit {
+    names: Vec<&'static str>,
+    inputs: Vec<(usize, bool)>,
+    gates: Vec<Gate>,
+}
+
+impl Circuit {
+    pub fn new(wires: &BTreeMap<&'static str, Wire>) -> Result<Self> {
+        let names = wires.keys().copied().collect::<Vec<_>>();
+        let index = |name: &str| {
+            names
+                .binary_search(&name)
+                .map_err(|_| anyhow!("Unknown wire {name}"))
+        };
+
+        let mut inputs = Vec::new();
+        let mut unsorted = Vec::new();
+        for (output, wire) in wires.values().enumerate() {
+            match wire {
+                Wire::Input(value) => inputs.push((output, *value)),
+                Wire::LogicGate {
+                    input1,
+                    operation,
+                    input2,
+                } => unsorted.push(Gate {
+                    input1: index(input1)?,
+                    operation: *operation,
+                    input2: index(input2)?,
+                    output,
+                }),
+            }
+        }
+
+        let mut consumers = vec![Vec::new(); names.len()];
+        for (gate_index, gate) in unsorted.iter().enumerate() {
+            consumers[gate.input1].push(gate_index);
+            consumers[gate.input2].push(gate_index);
+        }
+
+        let mut unresolved = vec![2; unsorted.len()];
+        let mut queue = inputs
+            .iter()
+            .map(|&(wire, _)| wire)
+            .collect::<VecDeque<_>>();
+        let mut gates = Vec::with_capacity(unsorted.len());
+        while let Some(wire) = queue.pop_front() {
+            for &gate_index in &consumers[wire] {
+                unresolved[gate_index] -= 1;
+                if unresolved[gate_index] == 0 {
+                    gates.push(unsorted[gate_index]);
+                    queue.push_back(unsorted[gate_index].output);
+                }
+            }
+        }
+
+        if gates.len() < unsorted.len() {
+            bail!("Circuit contains a loop");
+        }
+
+        let circuit = Self {
+            names,
+            inputs,
+            gates,
+        };
+        for prefix in ['x', 'y', 'z'] {
+            if circuit.bits(prefix) > u64::BITS as usize {
+                bail!("Circuit has more than {} {prefix}-wires", u64::BITS);
+            }
+        }
+        Ok(circuit)
+    }
+
+    /// Evaluates the circuit with the initial wire values from the input and returns the
+    /// number on the z-wires.
+    pub fn evaluate(&self) -> u64 {
+        let mut signals = vec![false; self.names.len()];
+        for &(wire, value) in &self.inputs {
+            signals[wire] = value;
+        }
+        self.propagate(&mut signals);
+        self.read('z', &signals)
+    }
+
+    /// Evaluates the circuit with the x-wires and y-wires set to the bits of `x` and `y`.
+    pub fn evaluate_with(&self, x: u64, y: u64) -> u64 {
+        let mut signals = vec![false; self.names.len()];
+        self.write('x', x, &mut signals);
+        self.write('y', y, &mut signals);
+        self.propagate(&mut signals);
+        self.read('z', &signals)
+    }
+
+    /// Adds `x` and `y` through the circuit and returns the z-bits that differ from the
+    /// real sum. Zero means the circuit added the numbers correctly. A carry out of the
+    /// highest bit has no z-wire to end up on, so the sum may wrap around.
+    pub fn addition_errors(&self, x: u64, y: u64) -> u64 {
+        let mask = u64::MAX
+            .checked_shr(u64::BITS - self.bits('z') as u32)
+            .unwrap_or_default();
+        (self.evaluate_with(x, y) ^ x.wrapping_add(y)) & mask
+    }
+
+    /// The names of the wires with the given prefix whose bits are set in `value`.
+    pub fn wires(&self, prefix: char, value: u64) -> Vec<&'static str> {
+        self.register(prefix)
+            .enumerate()
+            .filter(|&(bit, _)| value >> bit & 1 == 1)
+            .map(|(_, wire)| self.names[wire])
+            .collect()
+    }
+
+    pub fn bits(&self, prefix: char) -> usize {
+        self.register(prefix).count()
+    }
+
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph circuit {\n");
+        for (wire, name) in self.names.iter().enumerate() {
+            if self.inputs.iter().any(|&(input, _)| input == wire) {
+                writeln!(dot, "    {name} [shape=box];").unwrap();
+            } else if self.register('z').any(|output| output == wire) {
+                writeln!(dot, "    {name} [shape=doublecircle];").unwrap();
+            }
+        }
+        for (gate_index, gate) in self.gates.iter().enumerate() {
+            writeln!(
+                dot,
+                "    gate{gate_index} [label=\"{}\", shape=invtriangle];",
+                gate.operation
+            )
+            .unwrap();
+            writeln!(dot, "    {} -> gate{gate_index};", self.names[gate.input1]).unwrap();
+            writeln!(dot, "    {} -> gate{gate_index};", self.names[gate.input2]).unwrap();
+            writeln!(dot, "    gate{gate_index} -> {};", self.names[gate.output]).unwrap();
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    fn propagate(&self, signals: &mut [bool]) {
+        for gate in &self.gates {
+            signals[gate.output] = gate
+                .operation
+                .apply(signals[gate.input1], signals[gate.input2]);
+        }
+    }
+
+    fn register(&self, prefix: char) -> impl Iterator<Item = usize> + use<'_> {
+        self.names
+            .iter()
+            .enumerate()
+            .filter(move |(_, name)| {
+                name.strip_prefix(prefix)
+                    .is_some_and(|bit| bit.bytes().all(|b| b.is_ascii_digit()))
+            })
+            .map(|(wire, _)| wire)
+    }
+
+    fn read(&self, prefix: char, signals: &[bool]) -> u64 {
+        self.register(prefix)
+            .enumerate()
+            .fold(0, |acc, (bit, wire)| acc | (signals[wire] as u64) << bit)
+    }
+
+    fn write(&self, prefix: char, value: u64, signals: &mut [bool]) {
+        for (bit, wire) in self.register(prefix).enumerate() {
+            signals[wire] = value >> bit & 1 == 1;
+        }
+    }
+}
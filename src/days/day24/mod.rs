@@ -1,6 +1,9 @@
-use std::collections::BTreeMap;
+mod circuit;
 
-use memoize::memoize;
+use std::{collections::BTreeMap, fmt};
+
+use anyhow::bail;
+use circuit::Circuit;
 
 use crate::*;
 
@@ -37,20 +40,41 @@ impl From<&str> for Operation {
     }
 }
 
+impl Operation {
+    fn apply(self, value1: bool, value2: bool) -> bool {
+        match self {
+            Operation::And => value1 & value2,
+            Operation::Or => value1 | value2,
+            Operation::Xor => value1 ^ value2,
+        }
+    }
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operation::And => write!(f, "AND"),
+            Operation::Or => write!(f, "OR"),
+            Operation::Xor => write!(f, "XOR"),
+        }
+    }
+}
+
 impl Day {
+    pub fn dot() -> Result<String> {
+        Ok(Circuit::new(&Parser::parse(include_str!("input.txt"))?)?.to_dot())
+    }
+
+    pub fn add(x: u64, y: u64) -> Result<String> {
+        Self::addition(
+            &Circuit::new(&Parser::parse(include_str!("input.txt"))?)?,
+            x,
+            y,
+        )
+    }
+
     fn part1(wires: Parsed) -> Result<Output> {
-        Ok(wires
-            .keys()
-            .filter(|wire_name| wire_name.starts_with("z"))
-            .rev()
-            .fold(0, |acc, wire_name| {
-                acc * 2
-                    + if get_output(wire_name, wires.clone()) {
-                        1
-                    } else {
-                        0
-                    }
-            }))
+        Ok(Circuit::new(&wires)?.evaluate())
     }
 
     fn part2(_parsed: Parsed) -> Result<Output> {
@@ -58,23 +82,26 @@ impl Day {
     }
 }
 
-#[memoize]
-fn get_output(wire_name: &'static str, wires: BTreeMap<&'static str, Wire>) -> bool {
-    match wires.get(wire_name).unwrap() {
-        Wire::Input(value) => *value,
-        Wire::LogicGate {
-            input1,
-            operation,
-            input2,
-        } => {
-            let value1 = get_output(input1, wires.clone());
-            let value2 = get_output(input2, wires.clone());
-            match operation {
-                Operation::And => value1 & value2,
-                Operation::Or => value1 | value2,
-                Operation::Xor => value1 ^ value2,
+impl Day {
+    /// Adds two numbers through the circuit and shows which z-wires get a wrong value.
+    fn addition(circuit: &Circuit, x: u64, y: u64) -> Result<String> {
+        for (prefix, value) in [('x', x), ('y', y)] {
+            let bits = circuit.bits(prefix) as u32;
+            if value.checked_shr(bits).is_some_and(|rest| rest > 0) {
+                bail!("{value} does not fit on the {bits} {prefix}-wires");
             }
         }
+        let errors = circuit.addition_errors(x, y);
+        let mut addition = format!("{x} + {y} = {}\n", circuit.evaluate_with(x, y));
+        if errors == 0 {
+            addition.push_str("All z-wires are correct\n");
+        } else {
+            addition.push_str(&format!(
+                "Wrong z-wires: {}\n",
+                circuit.wires('z', errors).join(", ")
+            ));
+        }
+        Ok(addition)
     }
 }
 
@@ -114,4 +141,89 @@ mod tests {
     test_example!("example2", Part1, 2024);
 
     test_example!("example1", Part2, 0);
+
+    fn ripple_carry_adder(bits: usize) -> &'static str {
+        let mut input = String::new();
+        for bit in 0..bits {
+            input.push_str(&format!("x{bit:02}: 0\ny{bit:02}: 0\n"));
+        }
+        input.push('\n');
+        input.push_str("x00 XOR y00 -> z00\nx00 AND y00 -> c00\n");
+        for bit in 1..bits {
+            let carry = format!("c{:02}", bit - 1);
+            input.push_str(&format!(
+                "x{bit:02} XOR y{bit:02} -> h{bit:02}\n\
+                 x{bit:02} AND y{bit:02} -> a{bit:02}\n\
+                 h{bit:02} XOR {carry} -> z{bit:02}\n\
+                 h{bit:02} AND {carry} -> p{bit:02}\n\
+                 a{bit:02} OR p{bit:02} -> c{bit:02}\n"
+            ));
+        }
+        input.push_str(&format!(
+            "c{:02} OR c{:02} -> z{bits:02}",
+            bits - 1,
+            bits - 1
+        ));
+        input.leak()
+    }
+
+    #[test]
+    fn circuit_addition() -> Result<()> {
+        let circuit = Circuit::new(&Parser::parse(ripple_carry_adder(8))?)?;
+        assert_eq!(circuit.bits('z'), 9);
+        for (x, y) in [(0, 0), (1, 1), (255, 1), (170, 85), (200, 100), (255, 255)] {
+            assert_eq!(circuit.evaluate_with(x, y), x + y);
+            assert_eq!(circuit.addition_errors(x, y), 0);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn circuit_swapped_wires() -> Result<()> {
+        let input = ripple_carry_adder(4)
+            .replace("-> z01", "-> tmp")
+            .replace("-> a01", "-> z01")
+            .replace("-> tmp", "-> a01");
+        let circuit = Circuit::new(&Parser::parse(input.leak())?)?;
+        assert_eq!(circuit.addition_errors(1, 0), 0);
+        assert_ne!(circuit.addition_errors(2, 0), 0);
+        assert_eq!(
+            Day::addition(&circuit, 1, 2)?,
+            "1 + 2 = 5\nWrong z-wires: z01, z02\n"
+        );
+        assert!(Day::addition(&circuit, 16, 0).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn wide_circuit() -> Result<()> {
+        let circuit = Circuit::new(&Parser::parse(ripple_carry_adder(64))?);
+        assert!(circuit.is_err());
+        let circuit = Circuit::new(&Parser::parse(ripple_carry_adder(63))?)?;
+        assert_eq!(circuit.bits('z'), 64);
+        assert_eq!(circuit.addition_errors(u64::MAX >> 1, u64::MAX >> 1), 0);
+        assert_eq!(
+            Day::addition(&circuit, 3, 4)?,
+            "3 + 4 = 7\nAll z-wires are correct\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn circuit_with_loop() -> Result<()> {
+        let wires = Parser::parse("x00: 1\n\nx00 AND b -> a\na OR x00 -> b")?;
+        assert!(Circuit::new(&wires).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn circuit_dot() -> Result<()> {
+        let dot = Circuit::new(&Parser::parse(include_str!("example1.txt"))?)?.to_dot();
+        assert!(dot.starts_with("digraph circuit {"));
+        assert!(dot.contains("x00 [shape=box];"));
+        assert!(dot.contains("z02 [shape=doublecircle];"));
+        assert!(dot.contains("[label=\"XOR\", shape=invtriangle];"));
+        assert!(dot.contains("-> z01;"));
+        Ok(())
+    }
 }
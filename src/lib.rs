@@ -1,11 +1,16 @@
-#![feature(portable_simd)]
 #![allow(elided_named_lifetimes)]
 
 mod days;
+pub mod visualize;
 
-use std::fmt::{self, Debug};
+use std::{
+    fmt::{self, Debug},
+    path::Path,
+};
 
 pub use anyhow::Result;
+pub use days::day09::Strategy as Day09Strategy;
+pub use days::day18::Strategy as Day18Strategy;
 pub use Part::*;
 
 pub type IResult<'a, T> = nom::IResult<&'a str, T>;
@@ -45,6 +50,24 @@ pub fn run(day: u8, part: Part) -> String {
     .unwrap_or_else(|err| err.to_string())
 }
 
+/// Explains the solution of a part, for the days that support it.
+#[inline]
+pub fn explain(day: u8, part: Part) -> String {
+    match day {
+        2 => days::day02::Day::explain(part),
+        4 => days::day04::Day::explain(part),
+        7 => days::day07::Day::explain(part),
+        8 => days::day08::Day::explain(part),
+        11 => days::day11::Day::explain(part),
+        12 => days::day12::Day::explain(part),
+        16 => days::day16::Day::explain(part),
+        18 => days::day18::Day::explain(part),
+        20 => days::day20::Day::explain(part),
+        _ => Err(anyhow::anyhow!("Day {day} has no explanation")),
+    }
+    .unwrap_or_else(|err| err.to_string())
+}
+
 #[inline]
 pub fn bench_sample_size(day: u8) -> Option<usize> {
     match day {
@@ -77,6 +100,84 @@ pub fn bench_sample_size(day: u8) -> Option<usize> {
     }
 }
 
+#[inline]
+pub fn day03_highlight(part: Part) -> Result<String> {
+    days::day03::Day::highlight(part)
+}
+
+#[inline]
+pub fn day09_compact(strategy: Day09Strategy, show_layout: bool) -> Result<String> {
+    days::day09::Day::compact(strategy, show_layout)
+}
+
+#[inline]
+pub fn day10_trails(
+    x: usize,
+    y: usize,
+    max_climb: u8,
+    diagonal: bool,
+    limit: usize,
+) -> Result<(usize, Vec<String>)> {
+    days::day10::Day::trails(
+        x,
+        y,
+        days::day10::Rules {
+            max_climb,
+            diagonal,
+        },
+        limit,
+    )
+}
+
+#[inline]
+pub fn day14_tree(output: Option<&Path>, scale: usize) -> Result<String> {
+    days::day14::Day::tree(output, scale)
+}
+
+#[inline]
+pub fn day15_play() -> Result<()> {
+    days::day15::Day::play()
+}
+
+#[inline]
+pub fn day17_debug() -> Result<()> {
+    days::day17::Day::debug()
+}
+
+#[inline]
+pub fn day18_blocking(strategy: Day18Strategy) -> Result<String> {
+    days::day18::Day::blocking(strategy)
+}
+
+#[inline]
+pub fn day19_arrangements(design: &str, limit: usize) -> Result<(usize, Vec<String>)> {
+    days::day19::Day::arrangements(design, limit)
+}
+
+#[inline]
+pub fn day21_sequences(code: &str, robots: usize) -> Result<Vec<String>> {
+    days::day21::Day::sequences(code, robots)
+}
+
+#[inline]
+pub fn day22_bananas(
+    sequence: Option<[i8; 4]>,
+    buyers: &[usize],
+    iterations: usize,
+) -> Result<String> {
+    days::day22::Day::bananas(sequence, buyers, iterations)
+}
+
+#[inline]
+pub fn day24_dot() -> Result<String> {
+    days::day24::Day::dot()
+}
+
+#[inline]
+pub fn day24_add(x: u64, y: u64) -> Result<String> {
+    days::day24::Day::add(x, y)
+}
+
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub enum Part {
     Part1,
@@ -173,3 +274,24 @@ macro_rules! run {
         }
     };
 }
+
+/// A linear congruential generator for tests that check random inputs, so every run checks
+/// the same inputs.
+#[cfg(test)]
+pub(crate) struct Random(u64);
+
+#[cfg(test)]
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    /// A number below `n`.
+    pub fn below(&mut self, n: u64) -> u64 {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (self.0 >> 33) % n
+    }
+}
@@ -0,0 +1,300 @@
+#[cfg(feature = "visualize")]
+mod recorder;
+
+use std::{
+    ffi::OsStr,
+    fmt::Write as _,
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::bail;
+
+/// Records a frame when a recording was started with [`start`] on the same thread. The frame
+/// is only built when it will be recorded, and without the `visualize` feature this does
+/// nothing at all. Frames recorded on other threads, like the workers of a parallel
+/// iterator, are not part of the recording.
+#[inline]
+pub fn record<F: Frame>(frame: impl FnOnce() -> F) {
+    try_record(|| Ok(frame()));
+}
+
+/// Like [`record`], for frames that can fail to build. The error is returned by [`finish`].
+#[inline]
+pub fn try_record<F: Frame>(frame: impl FnOnce() -> crate::Result<F>) {
+    #[cfg(feature = "visualize")]
+    recorder::record(frame);
+    #[cfg(not(feature = "visualize"))]
+    let _ = frame;
+}
+
+#[cfg(feature = "visualize")]
+pub use recorder::{finish, start};
+
+#[cfg(not(feature = "visualize"))]
+pub fn start(_options: Options) -> crate::Result<()> {
+    bail!("Visualization is not available, build with `--features visualize`")
+}
+
+#[cfg(not(feature = "visualize"))]
+pub fn finish() -> crate::Result<usize> {
+    Ok(0)
+}
+
+/// Saves a single frame as ASCII art (`.txt`) or as an image (`.ppm`, or `.png` with the
+/// `visualize` feature), drawing every cell as `scale` by `scale` pixels.
+pub fn save(frame: &dyn Frame, path: &Path, scale: usize) -> crate::Result<()> {
+    match path.extension().and_then(OsStr::to_str) {
+        Some("txt") => fs::write(path, frame.to_ascii())?,
+        Some("ppm") => write_ppm(frame, path, scale)?,
+        #[cfg(feature = "visualize")]
+        Some("png") => recorder::write_png(frame, path, scale)?,
+        #[cfg(not(feature = "visualize"))]
+        Some("png") => {
+            bail!("Saving PNG images is not available, build with `--features visualize`")
+        }
+        _ => bail!(
+            "Cannot save a frame as {}, use .txt, .ppm or .png",
+            path.display()
+        ),
+    }
+    Ok(())
+}
+
+fn write_ppm(frame: &dyn Frame, path: &Path, scale: usize) -> crate::Result<()> {
+    let (width, height, pixels) = pixels(frame, scale);
+    let mut file = BufWriter::new(File::create(path)?);
+    write!(file, "P6\n{width} {height}\n255\n")?;
+    file.write_all(&pixels)?;
+    file.flush()?;
+    Ok(())
+}
+
+/// Renders every cell of the frame as a square of `scale` by `scale` RGB pixels.
+fn pixels(frame: &dyn Frame, scale: usize) -> (usize, usize, Vec<u8>) {
+    let (width, height) = frame.size();
+    let scale = scale.max(1);
+    let mut pixels = Vec::with_capacity(width * height * scale * scale * 3);
+    for y in 0..height {
+        let row = (0..width)
+            .flat_map(|x| {
+                let Rgb(r, g, b) = frame.cell(x, y).color;
+                [r, g, b].repeat(scale)
+            })
+            .collect::<Vec<_>>();
+        for _ in 0..scale {
+            pixels.extend_from_slice(&row);
+        }
+    }
+    (width * scale, height * scale, pixels)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Format {
+    /// Animation in the terminal using ANSI colours
+    Terminal,
+    /// One PPM image per frame
+    Ppm,
+    /// One PNG image per frame
+    Png,
+    /// A single animated GIF
+    Gif,
+}
+
+pub struct Options {
+    pub format: Format,
+    pub dir: PathBuf,
+    pub name: String,
+    pub scale: usize,
+    pub delay: Duration,
+    pub every: usize,
+    pub max_frames: usize,
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+impl Rgb {
+    pub const BLACK: Rgb = Rgb(0, 0, 0);
+    pub const WHITE: Rgb = Rgb(255, 255, 255);
+    pub const GRAY: Rgb = Rgb(96, 96, 96);
+    pub const RED: Rgb = Rgb(220, 40, 40);
+    pub const GREEN: Rgb = Rgb(40, 200, 70);
+    pub const BLUE: Rgb = Rgb(50, 100, 230);
+    pub const YELLOW: Rgb = Rgb(240, 210, 40);
+    pub const BROWN: Rgb = Rgb(150, 100, 50);
+
+    pub fn lerp(self, rhs: Rgb, t: f32) -> Rgb {
+        let channel = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t.clamp(0.0, 1.0)) as u8;
+        Rgb(
+            channel(self.0, rhs.0),
+            channel(self.1, rhs.1),
+            channel(self.2, rhs.2),
+        )
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct Cell {
+    pub symbol: char,
+    pub color: Rgb,
+}
+
+impl Cell {
+    pub fn new(symbol: char, color: Rgb) -> Self {
+        Self { symbol, color }
+    }
+}
+
+/// Something that can be drawn as a grid of coloured cells.
+pub trait Frame {
+    fn size(&self) -> (usize, usize);
+
+    fn cell(&self, x: usize, y: usize) -> Cell;
+
+    fn to_ascii(&self) -> String {
+        let (width, height) = self.size();
+        let mut ascii = String::with_capacity((width + 1) * height);
+        for y in 0..height {
+            for x in 0..width {
+                ascii.push(self.cell(x, y).symbol);
+            }
+            ascii.push('\n');
+        }
+        ascii
+    }
+
+    fn to_ansi(&self) -> String {
+        let (width, height) = self.size();
+        let mut ansi = String::new();
+        for y in 0..height {
+            let mut color = None;
+            for x in 0..width {
+                let cell = self.cell(x, y);
+                if color != Some(cell.color) {
+                    let Rgb(r, g, b) = cell.color;
+                    write!(ansi, "\x1b[38;2;{r};{g};{b}m").unwrap();
+                    color = Some(cell.color);
+                }
+                ansi.push(cell.symbol);
+            }
+            ansi.push_str("\x1b[0m\n");
+        }
+        ansi
+    }
+}
+
+impl<F: Frame> Frame for &F {
+    fn size(&self) -> (usize, usize) {
+        (*self).size()
+    }
+
+    fn cell(&self, x: usize, y: usize) -> Cell {
+        (*self).cell(x, y)
+    }
+}
+
+/// A frame that is drawn cell by cell, for days that don't implement [`Frame`] themselves.
+pub struct Canvas {
+    width: usize,
+    height: usize,
+    cells: Vec<Cell>,
+}
+
+impl Canvas {
+    pub fn new(width: usize, height: usize, fill: Cell) -> Self {
+        Self {
+            width,
+            height,
+            cells: vec![fill; width * height],
+        }
+    }
+
+    pub fn from_grid<T>(grid: &[Vec<T>], cell: impl Fn(&T) -> Cell) -> crate::Result<Self> {
+        let width = grid.first().map_or(0, Vec::len);
+        if grid.iter().any(|row| row.len() != width) {
+            bail!("All rows of a grid need to have the same length");
+        }
+        Ok(Self {
+            width,
+            height: grid.len(),
+            cells: grid.iter().flatten().map(cell).collect(),
+        })
+    }
+
+    /// Draws a cell, ignoring positions outside of the canvas.
+    pub fn set(&mut self, x: usize, y: usize, cell: Cell) {
+        if x < self.width && y < self.height {
+            self.cells[y * self.width + x] = cell;
+        }
+    }
+}
+
+impl Frame for Canvas {
+    fn size(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    fn cell(&self, x: usize, y: usize) -> Cell {
+        self.cells[y * self.width + x]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn canvas() -> crate::Result<()> {
+        let wall = |&wall: &bool| {
+            if wall {
+                Cell::new('#', Rgb::GRAY)
+            } else {
+                Cell::new('.', Rgb::BLACK)
+            }
+        };
+        let mut canvas = Canvas::from_grid(&[vec![false, true], vec![true, false]], wall)?;
+        canvas.set(0, 0, Cell::new('@', Rgb::RED));
+        canvas.set(5, 5, Cell::new('@', Rgb::RED));
+        assert_eq!(canvas.size(), (2, 2));
+        assert_eq!(canvas.to_ascii(), "@#\n#.\n");
+        assert_eq!(
+            canvas.to_ansi(),
+            "\x1b[38;2;220;40;40m@\x1b[38;2;96;96;96m#\x1b[0m\n\
+             \x1b[38;2;96;96;96m#\x1b[38;2;0;0;0m.\x1b[0m\n"
+        );
+        assert!(Canvas::from_grid(&[vec![false, true], vec![true]], wall).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn scaled_pixels() {
+        let mut canvas = Canvas::new(2, 1, Cell::new('.', Rgb::BLACK));
+        canvas.set(1, 0, Cell::new('#', Rgb(1, 2, 3)));
+        let (width, height, pixels) = pixels(&canvas, 2);
+        assert_eq!((width, height), (4, 2));
+        assert_eq!(pixels, [[0, 0, 0, 0, 0, 0, 1, 2, 3, 1, 2, 3]; 2].concat());
+    }
+
+    #[test]
+    fn save_frame() -> crate::Result<()> {
+        let dir = std::env::temp_dir().join("aoc2024_save_test");
+        fs::create_dir_all(&dir)?;
+        let canvas = Canvas::new(3, 2, Cell::new('#', Rgb::GREEN));
+        save(&canvas, &dir.join("frame.txt"), 1)?;
+        save(&canvas, &dir.join("frame.ppm"), 2)?;
+        assert_eq!(fs::read_to_string(dir.join("frame.txt"))?, "###\n###\n");
+        assert!(fs::read(dir.join("frame.ppm"))?.starts_with(b"P6\n6 4\n255\n"));
+        assert!(save(&canvas, &dir.join("frame.bmp"), 1).is_err());
+        fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn lerp() {
+        assert_eq!(Rgb::BLACK.lerp(Rgb::WHITE, 0.5), Rgb(127, 127, 127));
+        assert_eq!(Rgb::BLACK.lerp(Rgb::WHITE, 2.0), Rgb::WHITE);
+    }
+}
@@ -0,0 +1,201 @@
+use std::{
+    cell::RefCell,
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::Path,
+    thread,
+};
+
+use anyhow::bail;
+
+use super::{pixels, write_ppm, Format, Frame, Options};
+use crate::Result;
+
+thread_local! {
+    static RECORDER: RefCell<Option<Recorder>> = const { RefCell::new(None) };
+}
+
+struct Recorder {
+    options: Options,
+    skip: usize,
+    frames: usize,
+    size: Option<(usize, usize)>,
+    gif: Option<gif::Encoder<BufWriter<File>>>,
+    error: Option<anyhow::Error>,
+}
+
+/// Starts recording the frames of the solver that runs next on this thread. Only frames
+/// recorded on this thread are part of the recording.
+pub fn start(options: Options) -> Result<()> {
+    if options.every == 0 {
+        bail!("Recording every 0th frame is not possible");
+    }
+    if options.format == Format::Gif && gif_delay(&options).is_none() {
+        bail!(
+            "A GIF can not wait more than {} milliseconds between frames",
+            u16::MAX as u32 * 10
+        );
+    }
+    match options.format {
+        Format::Terminal => print!("\x1b[2J"),
+        _ => fs::create_dir_all(&options.dir)?,
+    }
+    RECORDER.set(Some(Recorder {
+        options,
+        skip: 0,
+        frames: 0,
+        size: None,
+        gif: None,
+        error: None,
+    }));
+    Ok(())
+}
+
+/// Stops recording and returns the number of recorded frames.
+pub fn finish() -> Result<usize> {
+    let Some(mut recorder) = RECORDER.take() else {
+        return Ok(0);
+    };
+    if let Some(error) = recorder.error {
+        return Err(error);
+    }
+    if let Some(gif) = recorder.gif.take() {
+        gif.into_inner()?.flush()?;
+    }
+    Ok(recorder.frames)
+}
+
+pub fn record<F: Frame>(frame: impl FnOnce() -> Result<F>) {
+    RECORDER.with_borrow_mut(|recorder| {
+        let Some(recorder) = recorder else {
+            return;
+        };
+        if recorder.skip > 0 {
+            recorder.skip -= 1;
+            return;
+        }
+        recorder.skip = recorder.options.every - 1;
+        if recorder.error.is_some() || recorder.frames >= recorder.options.max_frames {
+            return;
+        }
+        if let Err(error) = frame().and_then(|frame| recorder.write(&frame)) {
+            recorder.error = Some(error);
+        }
+    });
+}
+
+impl Recorder {
+    fn write(&mut self, frame: &dyn Frame) -> Result<()> {
+        let size = frame.size();
+        if *self.size.get_or_insert(size) != size {
+            bail!("All frames of a recording need to have the same size");
+        }
+
+        let path = |extension| {
+            self.options.dir.join(format!(
+                "{}_{:05}.{extension}",
+                self.options.name, self.frames
+            ))
+        };
+        match self.options.format {
+            Format::Terminal => {
+                let mut stdout = io::stdout().lock();
+                write!(stdout, "\x1b[H{}", frame.to_ansi())?;
+                stdout.flush()?;
+                thread::sleep(self.options.delay);
+            }
+            Format::Ppm => write_ppm(frame, &path("ppm"), self.options.scale)?,
+            Format::Png => write_png(frame, &path("png"), self.options.scale)?,
+            Format::Gif => {
+                let (width, height, pixels) = pixels(frame, self.options.scale);
+                if width > u16::MAX as usize || height > u16::MAX as usize {
+                    bail!("Frame of {width}x{height} pixels is too large for a GIF");
+                }
+                if self.gif.is_none() {
+                    let file =
+                        File::create(self.options.dir.join(format!("{}.gif", self.options.name)))?;
+                    let mut encoder =
+                        gif::Encoder::new(BufWriter::new(file), width as u16, height as u16, &[])?;
+                    encoder.set_repeat(gif::Repeat::Infinite)?;
+                    self.gif = Some(encoder);
+                }
+                let mut gif_frame =
+                    gif::Frame::from_rgb_speed(width as u16, height as u16, &pixels, 10);
+                gif_frame.delay = gif_delay(&self.options).unwrap_or(u16::MAX);
+                self.gif.as_mut().unwrap().write_frame(&gif_frame)?;
+            }
+        }
+
+        self.frames += 1;
+        Ok(())
+    }
+}
+
+/// The delay between frames in the hundredths of a second that a GIF uses.
+fn gif_delay(options: &Options) -> Option<u16> {
+    u16::try_from(options.delay.as_millis() / 10).ok()
+}
+
+pub(super) fn write_png(frame: &dyn Frame, path: &Path, scale: usize) -> Result<()> {
+    let (width, height, pixels) = pixels(frame, scale);
+    let file = BufWriter::new(File::create(path)?);
+    let mut encoder = png::Encoder::new(file, width as u32, height as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header()?.write_image_data(&pixels)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, time::Duration};
+
+    use super::*;
+    use crate::visualize::{self, Canvas, Cell, Rgb};
+
+    #[test]
+    fn recording() -> Result<()> {
+        let dir = env::temp_dir().join("aoc2024_visualize_test");
+        for format in [Format::Ppm, Format::Png, Format::Gif] {
+            visualize::start(Options {
+                format,
+                dir: dir.clone(),
+                name: format!("{format:?}"),
+                scale: 3,
+                delay: Duration::from_millis(100),
+                every: 2,
+                max_frames: 2,
+            })?;
+            for i in 0..10 {
+                visualize::record(|| Canvas::new(4, 3, Cell::new('.', Rgb(i * 20, 0, 0))));
+            }
+            assert_eq!(visualize::finish()?, 2);
+        }
+        assert!(fs::read(dir.join("Ppm_00001.ppm"))?.starts_with(b"P6\n12 9\n255\n"));
+        assert!(fs::read(dir.join("Png_00001.png"))?.starts_with(b"\x89PNG"));
+        assert!(fs::read(dir.join("Gif.gif"))?.starts_with(b"GIF89a"));
+        fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_recordings() -> Result<()> {
+        let dir = env::temp_dir().join("aoc2024_invalid_recording_test");
+        let options = |delay| Options {
+            format: Format::Gif,
+            dir: dir.clone(),
+            name: "invalid".to_string(),
+            scale: 1,
+            delay: Duration::from_millis(delay),
+            every: 1,
+            max_frames: 10,
+        };
+        assert!(visualize::start(options(655_360)).is_err());
+
+        visualize::start(options(655_350))?;
+        visualize::try_record(|| Canvas::from_grid(&[vec![1, 2], vec![3]], |_| Cell::default()));
+        assert!(visualize::finish().is_err());
+        fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+}